@@ -0,0 +1,172 @@
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use scrapper::types::Config;
+use scrapper::web_scraper::ContentExtractor;
+use std::hint::black_box;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const URL: &str = "https://example.com/chapter/1";
+
+/// Build a chapter page resembling a typical reader site: navigation, inline scripts,
+/// ads and comments around a `<main>` element holding `paragraphs` paragraphs.
+fn chapter_page(paragraphs: usize) -> String {
+    let mut html = String::from(
+        "<!DOCTYPE html><html><head><title>Chapter 1</title>\
+         <script>window.dataLayer = [];</script></head><body>\
+         <nav><a href=\"/\">Home</a> &gt; <a href=\"/series\">Series</a></nav>",
+    );
+
+    html.push_str("<main><h1>Chapter 1</h1><p class=\"author\">Posted by someone</p>");
+    for i in 0..paragraphs {
+        html.push_str(&format!(
+            "<p>Paragraph {i}: The quick brown fox jumps over the lazy dog while the \
+             <em>narrator</em> describes the scene in considerable detail.</p>"
+        ));
+        if i % 25 == 0 {
+            html.push_str("<div class=\"ad\">Advertisement</div>");
+        }
+    }
+    html.push_str("</main>");
+
+    html.push_str("<aside>");
+    for i in 0..paragraphs / 10 {
+        html.push_str(&format!(
+            "<div class=\"comment\">Comment {i}: thanks!</div>"
+        ));
+    }
+    html.push_str("</aside><footer>Privacy Policy | Terms of Service</footer></body></html>");
+
+    html
+}
+
+fn bench_extract_content(c: &mut Criterion) {
+    let extractor = ContentExtractor::new(&Config::default()).expect("default config is valid");
+
+    let mut group = c.benchmark_group("extract_content");
+    for (name, paragraphs) in [("small", 20), ("medium", 200), ("large", 2_000)] {
+        let html = chapter_page(paragraphs);
+        group.throughput(Throughput::Bytes(html.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(name), &html, |b, html| {
+            b.iter(|| extractor.extract_content(black_box(html), URL).unwrap())
+        });
+    }
+    group.finish();
+}
+
+/// Pages where the first configured selectors miss and a later fallback matches
+fn bench_selector_fallback(c: &mut Criterion) {
+    let config = Config {
+        selector: "#missing, .also-missing, article, .content".to_string(),
+        ..Config::default()
+    };
+    let extractor = ContentExtractor::new(&config).expect("selector is valid");
+    let html = chapter_page(200)
+        .replace("<main>", "<div class=\"content\">")
+        .replace("</main>", "</div>");
+
+    c.bench_function("extract_content/selector_fallback", |b| {
+        b.iter(|| extractor.extract_content(black_box(&html), URL).unwrap())
+    });
+}
+
+/// Large pages arriving at once
+const CONCURRENT_PAGES: usize = 16;
+
+/// A runtime with few workers, so parsing inline visibly competes with other tasks
+fn small_runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(2)
+        .enable_time()
+        .build()
+        .expect("runtime builds")
+}
+
+/// Extract `CONCURRENT_PAGES` large pages at once, inline on the worker threads or on the
+/// blocking pool, while a 1ms ticker runs. Returns the longest gap between ticks.
+async fn extract_concurrently(
+    extractor: &Arc<ContentExtractor>,
+    html: &Arc<str>,
+    blocking_pool: bool,
+) -> Duration {
+    // (last tick, longest gap), shared so a ticker that never got to run still counts
+    let ticks = Arc::new(Mutex::new((Instant::now(), Duration::ZERO)));
+    let ticker = tokio::spawn({
+        let ticks = Arc::clone(&ticks);
+        async move {
+            loop {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+                let mut ticks = ticks.lock().unwrap();
+                ticks.1 = ticks.1.max(ticks.0.elapsed());
+                ticks.0 = Instant::now();
+            }
+        }
+    });
+
+    let tasks: Vec<_> = (0..CONCURRENT_PAGES)
+        .map(|_| {
+            let extractor = Arc::clone(extractor);
+            let html = Arc::clone(html);
+            tokio::spawn(async move {
+                if blocking_pool {
+                    extractor.extract_content_async(html, URL).await.unwrap()
+                } else {
+                    extractor.extract_content(&html, URL).unwrap()
+                }
+            })
+        })
+        .collect();
+    for task in tasks {
+        black_box(task.await.unwrap());
+    }
+
+    ticker.abort();
+    let (last_tick, longest_gap) = *ticks.lock().unwrap();
+    longest_gap.max(last_tick.elapsed())
+}
+
+/// Total time to extract many large pages arriving at once
+fn bench_concurrent_extraction(c: &mut Criterion) {
+    let runtime = small_runtime();
+    let extractor = Arc::new(ContentExtractor::new(&Config::default()).expect("valid config"));
+    let html: Arc<str> = chapter_page(2_000).into();
+
+    let mut group = c.benchmark_group("concurrent_extraction");
+    group.throughput(Throughput::Bytes((html.len() * CONCURRENT_PAGES) as u64));
+    for (name, blocking_pool) in [("inline", false), ("blocking_pool", true)] {
+        group.bench_function(name, |b| {
+            b.iter(|| runtime.block_on(extract_concurrently(&extractor, &html, blocking_pool)))
+        });
+    }
+    group.finish();
+}
+
+/// How long other tasks on the runtime wait while many large pages are extracted: the
+/// reported time is the longest gap between ticks of a 1ms ticker per run, not the run's duration
+fn bench_runtime_stall(c: &mut Criterion) {
+    let runtime = small_runtime();
+    let extractor = Arc::new(ContentExtractor::new(&Config::default()).expect("valid config"));
+    let html: Arc<str> = chapter_page(2_000).into();
+
+    let mut group = c.benchmark_group("runtime_stall");
+    for (name, blocking_pool) in [("inline", false), ("blocking_pool", true)] {
+        group.bench_function(name, |b| {
+            b.iter_custom(|iters| {
+                (0..iters)
+                    .map(|_| {
+                        runtime.block_on(extract_concurrently(&extractor, &html, blocking_pool))
+                    })
+                    .sum()
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_extract_content,
+    bench_selector_fallback,
+    bench_concurrent_extraction,
+    bench_runtime_stall
+);
+criterion_main!(benches);
@@ -0,0 +1,1025 @@
+//! [`Scrapper`], the scraping pipeline behind the command line tool, for driving runs from
+//! other programs without going through argument parsing.
+
+use crate::csv_reader::{self, CsvReader};
+use crate::error::{ScrapperError, ScrapperResult};
+use crate::file_manager::FileManager;
+use crate::profile::RunProfile;
+use crate::progress::{ChapterLog, ProgressManager};
+#[cfg(feature = "sqlite")]
+use crate::sqlite_sink::SqliteSink;
+use crate::task_manager::{RetryBudget, RetryQueue, RetryRefusal, TaskManager};
+use crate::types::{ChapterRecord, Config, ScrapingStats};
+use crate::urls::{self, HostFilter};
+use crate::web_scraper::{self, RequestHook, ScrapeOutcome, WebScraper};
+use indicatif::{HumanBytes, HumanDuration, ProgressBar};
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::time::{Duration, Instant, sleep, timeout};
+
+/// Maximum number of retries for a single chapter after a recoverable error
+const MAX_RETRIES: usize = 3;
+
+/// Assumed time to fetch, extract and save one chapter, for the pre-run estimate
+const ESTIMATED_SECS_PER_CHAPTER: f64 = 1.0;
+
+/// Rough size and duration of the chapters about to be fetched
+#[derive(Debug, PartialEq)]
+struct RunEstimate {
+    chapters: usize,
+    /// From the average size of the chapter files already saved, if there are any
+    bytes: Option<u64>,
+    duration: Duration,
+}
+
+impl RunEstimate {
+    /// Estimate from the counts of the run's records, whose saved files were looked at then
+    fn new(chapters: usize, initial_stats: &ScrapingStats, config: &Config) -> Self {
+        let bytes = (initial_stats.saved_files > 0).then(|| {
+            let average = initial_stats.saved_bytes as f64 / initial_stats.saved_files as f64;
+            (average * chapters as f64) as u64
+        });
+
+        // Chapters start at most every `task_delay_ms` (or slower with `max_starts_per_sec`),
+        // and at most `max_concurrent_tasks` are fetched at once
+        let mut start_interval = config.task_delay_ms as f64 / 1000.0;
+        if let Some(rate) = config.max_starts_per_sec {
+            start_interval = start_interval.max(1.0 / rate);
+        }
+        let per_chapter = start_interval
+            .max(ESTIMATED_SECS_PER_CHAPTER / config.max_concurrent_tasks.max(1) as f64);
+
+        Self {
+            chapters,
+            bytes,
+            duration: Duration::from_secs_f64(per_chapter * chapters as f64),
+        }
+    }
+}
+
+impl std::fmt::Display for RunEstimate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} chapters to fetch, ", self.chapters)?;
+        match self.bytes {
+            Some(bytes) => write!(f, "~{} based on existing chapters", HumanBytes(bytes))?,
+            None => write!(f, "size unknown until some chapters exist")?,
+        }
+        write!(f, ", ~{}", HumanDuration(self.duration))
+    }
+}
+
+/// Why a run stopped before processing every record
+enum StopReason {
+    OutputLimit(String),
+    SuccessRateCollapsed(String),
+}
+
+impl std::fmt::Display for StopReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StopReason::OutputLimit(reason) => write!(f, "Output limit reached: {reason}"),
+            StopReason::SuccessRateCollapsed(reason) => {
+                write!(f, "Aborting, {reason}")
+            }
+        }
+    }
+}
+
+/// The whole scraping pipeline for one configuration: reads the input CSV, scrapes every
+/// chapter not yet saved with retries and limits, reports progress and writes the output
+pub struct Scrapper {
+    config: Config,
+    csv_reader: CsvReader,
+    file_manager: FileManager,
+    profile: Option<RunProfile>,
+    client: reqwest::Client,
+    request_hook: Option<RequestHook>,
+}
+
+/// Builds a [`Scrapper`] from a [`Config`], starting from the defaults
+#[derive(Default)]
+pub struct ScrapperBuilder {
+    config: Config,
+    request_hook: Option<RequestHook>,
+}
+
+impl ScrapperBuilder {
+    /// Replace the whole configuration, e.g. one loaded with `Config::from_file`
+    pub fn config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn input_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.input_file = path.into();
+        self
+    }
+
+    pub fn output_dir(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.output_dir = path.into();
+        self
+    }
+
+    pub fn selector(mut self, selector: impl Into<String>) -> Self {
+        self.config.selector = selector.into();
+        self
+    }
+
+    pub fn max_concurrent_tasks(mut self, max_concurrent_tasks: usize) -> Self {
+        self.config.max_concurrent_tasks = max_concurrent_tasks;
+        self
+    }
+
+    /// Call `hook` with each URL before requesting it and send the headers it returns, for
+    /// request signing or rotating tokens
+    pub fn request_hook(
+        mut self,
+        hook: impl Fn(&str) -> Vec<(String, String)> + Send + Sync + 'static,
+    ) -> Self {
+        self.request_hook = Some(Arc::new(hook));
+        self
+    }
+
+    /// Validate the configuration and set up the pipeline
+    pub fn build(self) -> ScrapperResult<Scrapper> {
+        let config = self.config;
+        config.validate()?;
+
+        let profile = config.profile.then(RunProfile::new);
+        // Built once: the input CSV, when it is a URL, is fetched like the chapters
+        let client = web_scraper::http_client(&config)?;
+        let csv_reader = CsvReader::new(&config).with_client(client.clone());
+        let file_manager = FileManager::new(&config);
+        #[cfg(feature = "sqlite")]
+        let file_manager = match &config.sqlite_database {
+            Some(path) => file_manager.with_database(SqliteSink::open(path)?),
+            None => file_manager,
+        };
+
+        Ok(Scrapper {
+            config,
+            csv_reader,
+            file_manager,
+            profile,
+            client,
+            request_hook: self.request_hook,
+        })
+    }
+}
+
+impl Scrapper {
+    pub fn builder() -> ScrapperBuilder {
+        ScrapperBuilder::default()
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Scrape the chapters of the input CSV, returning the run's statistics
+    pub async fn run(&self) -> ScrapperResult<ScrapingStats> {
+        let result = self.run_phases().await;
+        self.print_profile();
+        result
+    }
+
+    /// Scrape the given chapters instead of reading them from the input CSV
+    pub async fn run_records(&self, records: Vec<ChapterRecord>) -> ScrapperResult<ScrapingStats> {
+        let result = async {
+            self.file_manager.validate_output_dir().await?;
+            let initial_stats = self.initial_stats(&records);
+            let merge_records = self
+                .config
+                .merge_chapters_per_volume
+                .is_some()
+                .then(|| records.clone());
+            self.scrape(records, initial_stats, merge_records).await
+        }
+        .await;
+        self.print_profile();
+        result
+    }
+
+    fn print_profile(&self) {
+        if let Some(profile) = &self.profile {
+            println!("\n{}", profile.report());
+        }
+    }
+
+    /// Totals for a list of records that didn't come straight from the input CSV
+    fn initial_stats(&self, records: &[ChapterRecord]) -> ScrapingStats {
+        let mut stats = ScrapingStats {
+            total: records.len(),
+            ..ScrapingStats::default()
+        };
+        for record in records {
+            let saved_size = self.file_manager.saved_chapter_size(record);
+            if let Some(size) = saved_size {
+                stats.saved_files += 1;
+                stats.saved_bytes += size;
+            }
+            if self.file_manager.should_skip_saved(record, saved_size) {
+                stats.existing += 1;
+            }
+        }
+        stats
+    }
+
+    /// With `--profile`, record that a phase begun at `started` just finished
+    fn finish_phase(&self, name: &'static str, started: Instant) {
+        if let Some(profile) = &self.profile {
+            profile.finish_phase(name, started);
+        }
+    }
+
+    async fn run_phases(&self) -> ScrapperResult<ScrapingStats> {
+        // Validate CSV file format first
+        let phase = Instant::now();
+        if self.config.verbose {
+            println!("🔍 Validating CSV file format...");
+        }
+
+        self.csv_reader.validate_format().await?;
+
+        if self.config.verbose {
+            let csv_stats = self.csv_reader.get_stats().await?;
+            println!("📊 CSV Statistics:");
+            println!("   Total rows: {}", csv_stats.total_rows);
+            println!("   Valid rows: {}", csv_stats.valid_rows);
+            println!("   Invalid rows: {}", csv_stats.invalid_rows);
+            if csv_stats.blank_rows > 0 {
+                println!("   Blank rows skipped: {}", csv_stats.blank_rows);
+            }
+            if csv_stats.duplicate_rows > 0 {
+                println!("   Duplicate URLs skipped: {}", csv_stats.duplicate_rows);
+            }
+            println!("   Success rate: {:.1}%", csv_stats.success_rate());
+            for warning in &csv_stats.warnings {
+                println!("   ⚠️  {warning}");
+            }
+            println!();
+        } else if self.config.skip_invalid_rows {
+            let csv_stats = self.csv_reader.get_stats().await?;
+            if csv_stats.invalid_rows > 0 {
+                println!("⚠️  Skipping {} invalid CSV rows:", csv_stats.invalid_rows);
+                for warning in &csv_stats.warnings {
+                    println!("   {warning}");
+                }
+            }
+        }
+        self.finish_phase("CSV validation", phase);
+
+        // Ensure output directory exists and is writable
+        let phase = Instant::now();
+        self.file_manager.validate_output_dir().await?;
+
+        // Optional: Clean up any invalid files from previous runs, without holding up the run
+        if self.config.cleanup {
+            let budget = Duration::from_secs(self.config.cleanup_timeout_secs);
+            match timeout(budget, self.cleanup_previous_runs()).await {
+                Ok(result) => result?,
+                Err(_) => {
+                    println!(
+                        "⚠️  Cleanup stopped after {}s, scraping anyway; pass --skip-cleanup to skip it",
+                        self.config.cleanup_timeout_secs
+                    );
+                }
+            }
+        }
+
+        // Count total records and existing files
+        let mut initial_stats = self
+            .csv_reader
+            .count_records_and_existing(&self.file_manager)
+            .await?;
+        self.finish_phase("Output directory scan", phase);
+
+        // Read all records
+        let phase = Instant::now();
+        let mut records = self.csv_reader.read_records().await?;
+
+        // Volumes cover every chapter of the CSV, not only a sample
+        let merge_records = self
+            .config
+            .merge_chapters_per_volume
+            .is_some()
+            .then(|| records.clone());
+
+        if !self.config.only_chapters.is_empty() {
+            let (selected, unknown) =
+                csv_reader::select_chapters(records, &self.config.only_chapters);
+            if !unknown.is_empty() {
+                println!(
+                    "⚠️  No rows in {:?} for chapters: {}",
+                    self.config.input_file,
+                    unknown.join(", ")
+                );
+            }
+            if selected.is_empty() {
+                return Err(ScrapperError::validation(
+                    "only_chapters",
+                    "none of the requested chapters are in the input CSV",
+                ));
+            }
+            println!("🎯 Scraping {} selected chapters again", selected.len());
+            records = selected;
+            initial_stats = self.initial_stats(&records);
+        }
+
+        if let Some(exclude_file) = &self.config.exclude_file {
+            // Read with the same rules as the input, so chapter numbers match
+            let excluded = CsvReader::new(&Config {
+                input_file: exclude_file.clone(),
+                ..self.config.clone()
+            })
+            .read_records()
+            .await?;
+            let (kept, dropped) = csv_reader::exclude_chapters(records, &excluded);
+            println!(
+                "🚫 Leaving out {dropped} chapters listed in {exclude_file:?} ({} listed)",
+                excluded.len()
+            );
+            records = kept;
+            initial_stats = self.initial_stats(&records);
+        }
+
+        if let Some(amount) = self.config.sample {
+            let seed = self.config.sample_seed.unwrap_or_else(rand::random);
+            println!(
+                "🎲 Sampling {} of {} chapters (seed {seed}, pass --sample-seed {seed} to repeat)",
+                amount.min(records.len()),
+                records.len()
+            );
+            records = csv_reader::sample_records(records, amount, seed);
+            initial_stats = self.initial_stats(&records);
+        }
+
+        self.finish_phase("Reading records", phase);
+
+        self.scrape(records, initial_stats, merge_records).await
+    }
+
+    /// Remove empty and tiny chapter files left by earlier runs, unless the output directory
+    /// holds chapter files the CSV doesn't list: those may be someone else's, so they are only
+    /// touched with `force_cleanup`
+    async fn cleanup_previous_runs(&self) -> ScrapperResult<()> {
+        if !self.config.force_cleanup {
+            let records = self.csv_reader.read_records().await?;
+            let untracked = self.file_manager.untracked_chapter_files(&records).await?;
+            if !untracked.is_empty() {
+                const SHOWN: usize = 5;
+                let mut listed: Vec<String> = untracked
+                    .iter()
+                    .take(SHOWN)
+                    .filter_map(|path| path.file_name())
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .collect();
+                if untracked.len() > SHOWN {
+                    listed.push("...".to_string());
+                }
+                println!(
+                    "⚠️  {} chapter files in {:?} aren't listed in the CSV ({}); skipping cleanup so they aren't deleted",
+                    untracked.len(),
+                    self.config.output_dir,
+                    listed.join(", ")
+                );
+                println!("💡 Use a separate output directory, or pass --force to clean up anyway");
+                return Ok(());
+            }
+        }
+
+        if self.config.verbose {
+            println!("🧹 Cleaning up invalid files from previous runs...");
+        }
+        let cleanup_stats = self.file_manager.cleanup_invalid_files().await?;
+        if cleanup_stats.total_removed() > 0 {
+            println!(
+                "🧹 Removed {} empty or tiny chapter files from earlier runs",
+                cleanup_stats.total_removed()
+            );
+        }
+        Ok(())
+    }
+
+    /// Scrape the records not saved yet, then merge `merge_records` into volumes if requested
+    async fn scrape(
+        &self,
+        records: Vec<ChapterRecord>,
+        initial_stats: ScrapingStats,
+        merge_records: Option<Vec<ChapterRecord>>,
+    ) -> ScrapperResult<ScrapingStats> {
+        let records_to_process = initial_stats.records_to_process();
+        if records_to_process == 0 {
+            println!("✅ All files already exist. Nothing to process.");
+            if self.config.verbose {
+                println!("{}", initial_stats.summary_report());
+            }
+            self.merge_volumes(merge_records.as_deref()).await?;
+            return Ok(initial_stats);
+        }
+
+        println!(
+            "📋 Processing {} new chapters ({} already exist)",
+            records_to_process, initial_stats.existing
+        );
+
+        // Set expectations before a long run, and make a misconfigured delay stand out
+        println!(
+            "📐 Rough estimate: {} at {} concurrent tasks and {}ms delay",
+            RunEstimate::new(records_to_process, &initial_stats, &self.config),
+            self.config.max_concurrent_tasks,
+            self.config.task_delay_ms
+        );
+
+        // Initialize progress tracking
+        let progress = ProgressManager::new(records_to_process as u64, self.config.progress_json)?;
+
+        // Validate all records before processing
+        let phase = Instant::now();
+        if self.config.verbose {
+            println!("🔍 Validating {} records...", records.len());
+        }
+
+        for (i, record) in records.iter().enumerate() {
+            if let Err(e) = record.validate(&self.config.allowed_schemes) {
+                return Err(ScrapperError::validation(
+                    "record",
+                    format!("Invalid record at position {}: {}", i + 1, e),
+                ));
+            }
+        }
+        self.finish_phase("Record validation", phase);
+
+        // Process records concurrently
+        let stats = self
+            .process_records(records, initial_stats, &progress)
+            .await?;
+
+        self.merge_volumes(merge_records.as_deref()).await?;
+        Ok(stats)
+    }
+
+    /// With `--merge`, combine `records` (every chapter of the CSV, not only a `--sample`) into
+    /// volumes
+    async fn merge_volumes(&self, records: Option<&[ChapterRecord]>) -> ScrapperResult<()> {
+        let (Some(chapters_per_volume), Some(records)) =
+            (self.config.merge_chapters_per_volume, records)
+        else {
+            return Ok(());
+        };
+        let phase = Instant::now();
+
+        let merged = self
+            .file_manager
+            .merge_volumes(records, chapters_per_volume)
+            .await?;
+
+        println!(
+            "📚 Merged {} chapters into {} volumes of up to {chapters_per_volume}",
+            merged.chapters,
+            merged.volumes.len()
+        );
+        if !merged.missing.is_empty() {
+            const SHOWN: usize = 10;
+            let mut listed = merged.missing[..merged.missing.len().min(SHOWN)].join(", ");
+            if merged.missing.len() > SHOWN {
+                listed.push_str(", ...");
+            }
+            println!(
+                "⚠️  {} chapters are marked as missing in the volumes: {listed}",
+                merged.missing.len()
+            );
+        }
+        self.finish_phase("Merging volumes", phase);
+
+        Ok(())
+    }
+
+    async fn process_records(
+        &self,
+        records: Vec<ChapterRecord>,
+        mut stats: ScrapingStats,
+        progress: &ProgressManager,
+    ) -> ScrapperResult<ScrapingStats> {
+        let phase = Instant::now();
+        let mut tasks = TaskManager::new(self.config.max_concurrent_tasks)
+            .with_max_starts_per_sec(self.config.max_starts_per_sec);
+        let stats_pb = progress.get_stats_pb();
+
+        // One scraper shared by all tasks, so the HTTP client and per-run limits are shared too
+        let mut scraper = WebScraper::with_client(&self.config, self.client.clone())?;
+        if let Some(hook) = &self.request_hook {
+            scraper = scraper.with_request_hook(Arc::clone(hook));
+        }
+        let scraper = Arc::new(scraper);
+
+        // Track retry attempts for recoverable errors, bounded by the run-wide budget
+        let mut retry_queue =
+            RetryQueue::new(MAX_RETRIES, RetryBudget::new(self.config.max_total_retries));
+
+        let host_filter = HostFilter::new(&self.config.allowed_hosts, &self.config.blocked_hosts);
+
+        // Records left for a later run when we stop early, and why we stopped
+        let mut remaining: Vec<ChapterRecord> = Vec::new();
+        let mut stop_reason: Option<StopReason> = None;
+
+        // Records of hosts that used up `max_runtime_per_host_secs`, left for a later run
+        let mut over_budget: Vec<ChapterRecord> = Vec::new();
+        let mut exhausted_hosts: BTreeSet<String> = BTreeSet::new();
+
+        let mut pending = records.into_iter();
+        for record in pending.by_ref() {
+            // Skip existing files
+            if self.file_manager.should_skip(&record) {
+                if self.config.per_chapter_logging || self.config.verbose {
+                    progress.log_skip(&self.file_manager.chapter_file_name(&record));
+                }
+                progress.chapter_skipped(&record, "exists", &stats);
+                continue;
+            }
+
+            // Stop spawning once the output limits are reached or the site stopped cooperating
+            if let Some(reason) = self.stop_reason(&stats) {
+                stop_reason = Some(reason);
+                remaining.push(record);
+                break;
+            }
+
+            // Skip hosts we were not asked to scrape, before making any request
+            if let Some(reason) = host_filter.rejection_reason(&record.url) {
+                progress.log_filtered(&record.chapter_number, &reason);
+                stats.filtered_by_host += 1;
+                progress.increment_progress();
+                progress.chapter_skipped(&record, &reason, &stats);
+                continue;
+            }
+
+            // Move on to other hosts once this one used up its runtime budget
+            if self.host_runtime_exhausted(&record, &stats, &mut exhausted_hosts, progress) {
+                over_budget.push(record);
+                continue;
+            }
+
+            progress.chapter_started(&record, 1, &stats);
+
+            // Clone data needed for the async task
+            if let Some((finished, elapsed, result)) = tasks
+                .spawn_or_wait(|| {
+                    let chapter_path = self.file_manager.get_chapter_path(&record);
+                    let chapter_log = self.chapter_progress_log(progress, &record, &stats_pb);
+                    let scraper = Arc::clone(&scraper);
+                    let show_chapter_progress = self.config.chapter_progress;
+
+                    async move {
+                        let started = Instant::now();
+                        let result = scraper
+                            .scrape_chapter(&record, &chapter_path, Some(&chapter_log))
+                            .await;
+                        if show_chapter_progress {
+                            chapter_log.finish_and_clear();
+                        }
+                        (record, started.elapsed(), result)
+                    }
+                })
+                .await
+            {
+                stats.record_attempt(&finished, elapsed, result.is_ok());
+                self.handle_task_result(
+                    finished,
+                    0,
+                    Ok(result),
+                    &mut stats,
+                    progress,
+                    &mut retry_queue,
+                );
+            }
+
+            // Update progress displays
+            progress.update_active_tasks(tasks.len());
+            progress.update_stats_with_queue(&stats, tasks.len());
+            sleep(Duration::from_millis(self.config.task_delay_ms)).await;
+        }
+        remaining.extend(pending);
+
+        // Wait for all remaining tasks to complete
+        let remaining_results = tasks.join_all().await;
+        for (finished, elapsed, result) in remaining_results {
+            stats.record_attempt(&finished, elapsed, result.is_ok());
+            self.handle_task_result(
+                finished,
+                0,
+                Ok(result),
+                &mut stats,
+                progress,
+                &mut retry_queue,
+            );
+
+            // Update progress displays
+            progress.update_active_tasks(tasks.len());
+            progress.update_stats_with_remaining(&stats, tasks.len());
+        }
+
+        self.finish_phase("Scraping", phase);
+
+        // Process retry queue for recoverable errors
+        let phase = Instant::now();
+        if !retry_queue.is_empty() && self.config.verbose {
+            progress.log_info(&format!(
+                "Processing {} items from retry queue...",
+                retry_queue.len()
+            ));
+        }
+
+        // Retries run with their own, usually lower, concurrency
+        let mut retry_tasks: TaskManager<(_, _, _, ScrapperResult<ScrapeOutcome>)> =
+            TaskManager::new(self.config.retry_concurrency)
+                .with_max_starts_per_sec(self.config.max_starts_per_sec);
+        loop {
+            let Some((record, retry_count)) = retry_queue.pop() else {
+                // In-flight retries may fail again and queue more work
+                match retry_tasks.join_next().await {
+                    Some((finished, retry_count, elapsed, result)) => {
+                        stats.record_attempt(&finished, elapsed, result.is_ok());
+                        self.handle_task_result(
+                            finished,
+                            retry_count,
+                            Ok(result),
+                            &mut stats,
+                            progress,
+                            &mut retry_queue,
+                        );
+                        continue;
+                    }
+                    None => break,
+                }
+            };
+
+            if stop_reason.is_none() {
+                stop_reason = self.stop_reason(&stats);
+            }
+            if stop_reason.is_some() {
+                remaining.push(record);
+                continue;
+            }
+            if self.host_runtime_exhausted(&record, &stats, &mut exhausted_hosts, progress) {
+                over_budget.push(record);
+                continue;
+            }
+
+            progress.chapter_started(&record, retry_count + 1, &stats);
+
+            if let Some((finished, retry_count, elapsed, result)) = retry_tasks
+                .spawn_or_wait(|| {
+                    let chapter_path = self.file_manager.get_chapter_path(&record);
+                    let chapter_log = self.chapter_progress_log(progress, &record, &stats_pb);
+                    let scraper = Arc::clone(&scraper);
+                    let show_chapter_progress = self.config.chapter_progress;
+                    // Exponential backoff for retries
+                    let delay = Duration::from_millis(
+                        self.config.task_delay_ms * (2_u64.pow(retry_count as u32)),
+                    );
+
+                    async move {
+                        sleep(delay).await;
+                        let started = Instant::now();
+                        let result = scraper
+                            .scrape_chapter(&record, &chapter_path, Some(&chapter_log))
+                            .await;
+                        if show_chapter_progress {
+                            chapter_log.finish_and_clear();
+                        }
+                        (record, retry_count, started.elapsed(), result)
+                    }
+                })
+                .await
+            {
+                stats.record_attempt(&finished, elapsed, result.is_ok());
+                self.handle_task_result(
+                    finished,
+                    retry_count,
+                    Ok(result),
+                    &mut stats,
+                    progress,
+                    &mut retry_queue,
+                );
+            }
+            progress.update_active_tasks(retry_tasks.len());
+        }
+        self.finish_phase("Retries", phase);
+
+        if let Some(reason) = &stop_reason {
+            remaining.extend(over_budget);
+            self.defer_remaining(remaining, &reason.to_string(), &mut stats, progress)
+                .await?;
+        } else if !over_budget.is_empty() {
+            let hosts: Vec<String> = exhausted_hosts.into_iter().collect();
+            let reason = format!(
+                "Runtime budget of {}s per host used up for {}",
+                self.config.max_runtime_per_host_secs.unwrap_or_default(),
+                hosts.join(", ")
+            );
+            self.defer_remaining(over_budget, &reason, &mut stats, progress)
+                .await?;
+        }
+
+        // Finish progress display
+        progress.finish(&stats);
+
+        // Many chapters of the same size usually means boilerplate was extracted
+        if let Some(cluster) = self.file_manager.uniform_size_cluster().await? {
+            println!(
+                "\n⚠️  {} of {} chapter files are about {} bytes. They may contain a repeated notice \
+                 (e.g. \"login required\") rather than chapter content; check a few of them.",
+                cluster.count, cluster.total, cluster.size
+            );
+        }
+
+        // Show final recommendations
+        let recommendations = stats.get_recommendations();
+        if !recommendations.is_empty() {
+            println!("\n💡 Recommendations:");
+            for rec in recommendations {
+                println!("   • {rec}");
+            }
+        }
+
+        // Show detailed stats if verbose
+        if self.config.verbose {
+            println!("\n{}", stats.summary_report());
+
+            // Show which filter patterns are pulling their weight
+            let filter_hits = scraper.filter_hits();
+            if !filter_hits.is_empty() {
+                println!("\n🧹 Filter pattern hits (text nodes removed):");
+                for (pattern, hits) in filter_hits {
+                    let note = if hits == 0 { " (never matched)" } else { "" };
+                    println!("   {pattern:?}: {hits}{note}");
+                }
+            }
+
+            // Show file system statistics
+            let fs_stats = self.file_manager.get_existing_files_info().await?;
+            println!("\n📁 File System Statistics:");
+            println!("   Total files: {}", fs_stats.total_files);
+            println!("   Valid files: {}", fs_stats.valid_files());
+            println!("   Empty files: {}", fs_stats.empty_files);
+            println!(
+                "   Average file size: {:.1} bytes",
+                fs_stats.average_file_size()
+            );
+        }
+
+        // Validate final progress state
+        progress.validate_progress_state()?;
+
+        if let Some(StopReason::SuccessRateCollapsed(reason)) = stop_reason {
+            return Err(ScrapperError::aborted(reason));
+        }
+
+        Ok(stats)
+    }
+
+    /// Where a chapter task reports: its own line when per-chapter progress is on, otherwise
+    /// the shared stats bar
+    fn chapter_progress_log(
+        &self,
+        progress: &ProgressManager,
+        record: &ChapterRecord,
+        stats_pb: &ProgressBar,
+    ) -> ChapterLog {
+        if self.config.chapter_progress {
+            progress.chapter_log(progress.chapter_bar(record))
+        } else {
+            progress.chapter_log(stats_pb.clone())
+        }
+    }
+
+    /// Why no new chapters should be started, if the run has to stop early
+    fn stop_reason(&self, stats: &ScrapingStats) -> Option<StopReason> {
+        if let Some(reason) = self.file_manager.output_limit_reached() {
+            return Some(StopReason::OutputLimit(reason));
+        }
+
+        let threshold = self.config.abort_below_success_rate?;
+        stats
+            .collapsed_success_rate(threshold, self.config.abort_min_samples)
+            .map(|recent| {
+                StopReason::SuccessRateCollapsed(format!(
+                    "recent success rate {recent:.1}% fell below {threshold:.1}% after {} attempts",
+                    stats.attempts
+                ))
+            })
+    }
+
+    /// Whether the record's host already used up `max_runtime_per_host_secs`; warns the first
+    /// time a host runs out
+    fn host_runtime_exhausted(
+        &self,
+        record: &ChapterRecord,
+        stats: &ScrapingStats,
+        exhausted_hosts: &mut BTreeSet<String>,
+        progress: &ProgressManager,
+    ) -> bool {
+        let Some(limit) = self.config.max_runtime_per_host_secs else {
+            return false;
+        };
+        let spent = stats.runtime_for_host_of(&record.url);
+        if spent < Duration::from_secs(limit) {
+            return false;
+        }
+
+        let host = urls::host_of(&record.url).unwrap_or_default();
+        if exhausted_hosts.insert(host.clone()) {
+            progress.log_warning(&format!(
+                "Spent {:.0}s on {host}, its remaining chapters are left for a later run",
+                spent.as_secs_f64()
+            ));
+        }
+        true
+    }
+
+    /// Account for a finished attempt at a chapter that had already been retried `retries_done` times
+    fn handle_task_result(
+        &self,
+        record: ChapterRecord,
+        retries_done: usize,
+        result: Result<ScrapperResult<ScrapeOutcome>, tokio::task::JoinError>,
+        stats: &mut ScrapingStats,
+        progress: &ProgressManager,
+        retry_queue: &mut RetryQueue<ChapterRecord>,
+    ) {
+        match result {
+            Ok(Ok(outcome)) if outcome.skipped_empty => {
+                stats.skipped_empty += 1;
+                progress.increment_progress();
+                progress.chapter_skipped(&record, "no content after filtering", stats);
+            }
+            Ok(Ok(outcome)) if outcome.kept_existing => {
+                stats.kept_existing += 1;
+                progress.increment_progress();
+                progress.chapter_skipped(&record, "existing file is larger", stats);
+            }
+            Ok(Ok(outcome)) => {
+                self.file_manager.record_write(outcome.bytes_written);
+                stats.increment_success();
+                if let Some(change) = outcome.refresh {
+                    stats.record_refresh(&record.chapter_number, change);
+                }
+                progress.increment_progress();
+                progress.chapter_finished(&record, outcome.bytes_written, stats);
+            }
+            Ok(Err(e)) => {
+                if e.is_recoverable() {
+                    self.retry_or_give_up(record, retries_done, &e, stats, progress, retry_queue);
+                } else {
+                    stats.increment_permanent_error(e.kind());
+                    progress.log_error(&e);
+                    progress.increment_progress();
+                    progress.chapter_failed(&record, &e, false, stats);
+                }
+            }
+            Err(e) => {
+                let scrapper_error = ScrapperError::task_execution(e.to_string());
+                stats.increment_permanent_error(scrapper_error.kind());
+                progress.log_error(&scrapper_error);
+                progress.increment_progress();
+                progress.chapter_failed(&record, &scrapper_error, false, stats);
+            }
+        }
+    }
+
+    /// Write records we stopped before processing to a CSV so a later run can pick them up
+    async fn defer_remaining(
+        &self,
+        mut remaining: Vec<ChapterRecord>,
+        reason: &str,
+        stats: &mut ScrapingStats,
+        progress: &ProgressManager,
+    ) -> ScrapperResult<()> {
+        remaining.retain(|record| !self.file_manager.should_skip(record));
+        stats.deferred = remaining.len();
+        progress.set_total_to_processed();
+
+        if remaining.is_empty() {
+            progress.log_warning(&format!("{reason}. Stopping."));
+            return Ok(());
+        }
+
+        let path = self.file_manager.remaining_records_path();
+        csv_reader::write_records(&path, &remaining).await?;
+        progress.log_warning(&format!(
+            "{reason}. Stopping; {} remaining chapters written to {path:?}",
+            remaining.len()
+        ));
+
+        Ok(())
+    }
+
+    /// Queue a recoverable failure for another attempt, or record it as a final error
+    fn retry_or_give_up(
+        &self,
+        record: ChapterRecord,
+        retries_done: usize,
+        error: &ScrapperError,
+        stats: &mut ScrapingStats,
+        progress: &ProgressManager,
+        retry_queue: &mut RetryQueue<ChapterRecord>,
+    ) {
+        let failed = record.clone();
+        let chapter_number = &failed.chapter_number;
+
+        match retry_queue.schedule(record, retries_done) {
+            Ok(()) => {
+                progress.chapter_failed(&failed, error, true, stats);
+                return;
+            }
+            Err(RetryRefusal::MaxRetriesExceeded) => {
+                progress.log_warning(&format!(
+                    "Max retries exceeded for chapter {chapter_number}"
+                ));
+                stats.increment_recoverable_error(error.kind());
+            }
+            Err(RetryRefusal::BudgetExhausted) => {
+                progress.log_warning(&format!(
+                    "Run-wide retry budget exhausted; treating chapter {chapter_number} as a permanent failure"
+                ));
+                stats.increment_budget_exhausted_error(error.kind());
+            }
+        }
+
+        progress.log_error(error);
+        progress.increment_progress();
+        progress.chapter_failed(&failed, error, false, stats);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TempDir;
+
+    #[test]
+    fn test_run_estimate() {
+        let existing = ScrapingStats {
+            total: 110,
+            existing: 10,
+            saved_files: 10,
+            saved_bytes: 10 * 20_000,
+            ..ScrapingStats::default()
+        };
+        let config = Config {
+            max_concurrent_tasks: 4,
+            task_delay_ms: 100,
+            ..Config::default()
+        };
+
+        // Limited by concurrency: 4 chapters per assumed second
+        let estimate = RunEstimate::new(100, &existing, &config);
+        assert_eq!(estimate.bytes, Some(2_000_000));
+        assert_eq!(estimate.duration, Duration::from_secs(25));
+
+        // Limited by the start rate
+        let paced = Config {
+            max_starts_per_sec: Some(2.0),
+            ..config
+        };
+        let estimate = RunEstimate::new(100, &ScrapingStats::default(), &paced);
+        assert_eq!(estimate.bytes, None);
+        assert_eq!(estimate.duration, Duration::from_secs(50));
+        assert!(estimate.to_string().contains("size unknown"));
+    }
+
+    #[tokio::test]
+    async fn test_run_records_scrapes_local_pages() {
+        let dir = TempDir::new("app");
+        let page = dir.join("chapter.html");
+        tokio::fs::write(
+            &page,
+            format!("<html><main>{}</main></html>", "Chapter text. ".repeat(20)),
+        )
+        .await
+        .unwrap();
+
+        let scrapper = Scrapper::builder()
+            .config(Config {
+                allowed_schemes: vec!["file".to_string()],
+                skip_text_nodes: 0,
+                ..Config::default()
+            })
+            .output_dir(dir.join("out"))
+            .selector("main")
+            .build()
+            .unwrap();
+        let record = ChapterRecord::new(
+            url::Url::from_file_path(&page).unwrap().to_string(),
+            "1".to_string(),
+        );
+        let stats = scrapper.run_records(vec![record.clone()]).await;
+        let saved = tokio::fs::read_to_string(dir.join("out").join("chapter_1.txt")).await;
+
+        let stats = stats.unwrap();
+        assert_eq!((stats.total, stats.success_count), (1, 1));
+        assert!(saved.unwrap().starts_with("Chapter text."));
+    }
+}
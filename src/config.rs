@@ -1,9 +1,14 @@
 use crate::error::{ScrapperError, ScrapperResult};
+use crate::urls::{
+    self, DEFAULT_ALLOWED_SCHEMES, DEFAULT_TRACKING_PARAMS, FALLBACK_CHAPTER_PLACEHOLDER,
+    SUPPORTED_SCHEMES,
+};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use tokio::fs;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ScrapingConfig {
     /// Maximum number of concurrent scraping tasks
     pub max_concurrent_tasks: usize,
@@ -11,7 +16,7 @@ pub struct ScrapingConfig {
     /// Delay between spawning tasks (milliseconds)
     pub task_delay_ms: u64,
     
-    /// Path to input CSV file
+    /// Path to input CSV file, or an `http(s)://` URL it is downloaded from
     pub input_file: PathBuf,
     
     /// Output directory for scraped files
@@ -34,6 +39,284 @@ pub struct ScrapingConfig {
     
     /// Enable verbose logging
     pub verbose: bool,
+
+    /// File name prefix for the write-permission probe created in the output directory
+    pub write_probe_prefix: String,
+
+    /// Skip the write-permission probe (for directories already known to be writable)
+    pub skip_write_check: bool,
+
+    /// How chapters are laid out in the output directory
+    pub output_layout: OutputLayout,
+
+    /// Directory to save the raw HTML of pages whose content extraction failed
+    pub dump_html_on_failure: Option<PathBuf>,
+
+    /// Maximum number of HTML dumps written per run
+    pub max_html_dumps: usize,
+
+    /// Maximum size of a single HTML dump (bytes); larger pages are truncated
+    pub max_html_dump_bytes: usize,
+
+    /// Directory to save the text of chapters rejected as too short, for manual review
+    pub save_partial_on_failure: Option<PathBuf>,
+
+    /// Maximum number of retry attempts across the whole run (unlimited if unset)
+    pub max_total_retries: Option<usize>,
+
+    /// URL schemes accepted for chapter links
+    pub allowed_schemes: Vec<String>,
+
+    /// Only scrape URLs whose host matches one of these patterns (all hosts if empty)
+    pub allowed_hosts: Vec<String>,
+
+    /// Never scrape URLs whose host matches one of these patterns
+    pub blocked_hosts: Vec<String>,
+
+    /// Accept invalid TLS certificates (self-signed, expired, wrong host). Dangerous!
+    pub danger_accept_invalid_certs: bool,
+
+    /// Extra root certificate (PEM or DER) to trust, e.g. an internal CA
+    pub root_cert: Option<PathBuf>,
+
+    /// Stop the run once this many chapter files have been written
+    pub max_output_files: Option<usize>,
+
+    /// Stop the run once this many bytes have been written
+    pub max_output_bytes: Option<u64>,
+
+    /// Save the HTTP status and response headers next to each chapter
+    pub save_headers: bool,
+
+    /// Archive every fetched page's request and raw response to this WARC file
+    pub warc_file: Option<PathBuf>,
+
+    /// Maximum concurrent tasks while retrying recoverable failures
+    pub retry_concurrency: usize,
+
+    /// Stream progress events as JSON lines to stderr instead of drawing progress bars
+    pub progress_json: bool,
+
+    /// Extension for chapter content files, overriding the default `txt`
+    pub output_extension: Option<String>,
+
+    /// Abort the run when the recent success rate (percent) drops below this
+    pub abort_below_success_rate: Option<f64>,
+
+    /// Attempts required before `abort_below_success_rate` is checked
+    pub abort_min_samples: usize,
+
+    /// Maximum length of generated file names (bytes); longer names are truncated with a hash suffix
+    pub max_filename_len: usize,
+
+    /// Also extract text from markup hidden inside HTML comments
+    pub extract_from_comments: bool,
+
+    /// Parse `<noscript>` blocks as HTML and extract their text instead of their raw markup
+    pub include_noscript: bool,
+
+    /// URL tried when a chapter's URL returns 404, with `{chapter}` replaced by the chapter number
+    pub fallback_url_template: Option<String>,
+
+    /// Show a progress line per in-flight chapter (fetching, extracting, image 3/7, ...)
+    pub chapter_progress: bool,
+
+    /// What to do with chapters that already exist in the output directory
+    pub existing_policy: ExistingPolicy,
+
+    /// With `existing_policy = "overwrite-smaller"`, how many times larger new content must be
+    /// than the existing file to replace it
+    pub overwrite_min_growth: f64,
+
+    /// Load pages in a headless browser so JavaScript-rendered content is extracted
+    /// (requires the `render` cargo feature)
+    pub render: bool,
+
+    /// With `render`, wait until this CSS selector matches before reading the page
+    pub wait_for_selector: Option<String>,
+
+    /// How long to wait for `wait_for_selector` before failing the chapter (milliseconds)
+    pub wait_timeout_ms: u64,
+
+    /// With `render`, extra time to wait after the page loaded (and `wait_for_selector` matched)
+    /// before reading it, for content still streaming in (milliseconds)
+    pub render_settle_ms: u64,
+
+    /// Scrape a random sample of this many chapters instead of the whole CSV
+    pub sample: Option<usize>,
+
+    /// Seed for `sample`, so the same chapters are picked again (random if unset)
+    pub sample_seed: Option<u64>,
+
+    /// Scrape only these chapter numbers from the CSV, replacing their existing files
+    pub only_chapters: Vec<String>,
+
+    /// Leave out the chapters listed in this CSV (e.g. `verify_failures.csv` from an earlier
+    /// run), in the same `url,chapter_number` format as the input
+    pub exclude_file: Option<PathBuf>,
+
+    /// Store chapters in this SQLite database instead of text files (requires the `sqlite`
+    /// cargo feature)
+    pub sqlite_database: Option<PathBuf>,
+
+    /// After scraping, concatenate chapters in CSV order into `volume_N` files of this many
+    /// chapters each
+    pub merge_chapters_per_volume: Option<usize>,
+
+    /// `Accept-Language` header sent with every HTTP request, for sites serving translations
+    /// of the same URL
+    pub accept_language: Option<String>,
+
+    /// Credentials sent as an `Authorization` header with every HTTP request
+    pub auth: Option<Auth>,
+
+    /// Cookies exported from a browser in the Netscape `cookies.txt` format, sent with requests
+    /// to the sites they belong to
+    pub cookies_file: Option<PathBuf>,
+
+    /// Decode every page with this encoding (e.g. `"windows-1252"`) instead of the charset it
+    /// declares, for sites that declare the wrong one
+    pub force_encoding: Option<String>,
+
+    /// Fail chapters whose decoded text is full of U+FFFD replacement characters, which means
+    /// the page was decoded with the wrong charset
+    pub detect_encoding_errors: bool,
+
+    /// Text that marks a short chapter file as a saved error page when checking with `--verify`
+    pub error_page_markers: Vec<String>,
+
+    /// Whether a content element with no text left after filtering fails the chapter or skips it
+    pub empty_content_policy: EmptyContentPolicy,
+
+    /// Cumulative time (seconds) spent scraping any single host before its remaining chapters
+    /// are left for a later run, so one large series doesn't starve the other hosts
+    pub max_runtime_per_host_secs: Option<u64>,
+
+    /// With `existing_policy = "refresh"`, keep the previous version of a changed chapter as
+    /// `<file>.bak`
+    pub refresh_keep_backup: bool,
+
+    /// Start at most this many chapters per second, independently of `max_concurrent_tasks`
+    /// and on top of `task_delay_ms`
+    pub max_starts_per_sec: Option<f64>,
+
+    /// Print how long each phase of the run took at the end
+    pub profile: bool,
+
+    /// Regex whose first capture group gives a record's chapter number from its URL, used
+    /// when the CSV chapter column is empty or missing
+    pub chapter_from_url_regex: Option<String>,
+
+    /// With `chapter_from_url_regex`, take chapter numbers from URLs even when the CSV has one
+    pub chapter_from_url_always: bool,
+
+    /// Turn chapter numbers captured from URLs like `0012` into `12`
+    pub strip_chapter_leading_zeros: bool,
+
+    /// Skip malformed CSV rows, listing them with their line numbers, instead of stopping
+    pub skip_invalid_rows: bool,
+
+    /// Compare URLs without `tracking_params`, leaving out rows whose URL only differs from an
+    /// earlier one by those
+    pub canonicalize_urls: bool,
+
+    /// Query parameters ignored by `canonicalize_urls`; a trailing `*` matches a prefix
+    pub tracking_params: Vec<String>,
+
+    /// With `canonicalize_urls`, also request the URLs without `tracking_params`
+    pub strip_on_request: bool,
+
+    /// Lines dropped from the start of the extracted text, after filtering
+    pub trim_leading_lines: usize,
+
+    /// Lines dropped from the end of the extracted text, after filtering
+    pub trim_trailing_lines: usize,
+
+    /// Remove empty and tiny chapter files left by earlier runs before scraping
+    pub cleanup: bool,
+
+    /// Give up on the cleanup pass after this long and start scraping anyway (seconds)
+    pub cleanup_timeout_secs: u64,
+
+    /// Run the cleanup pass even when the output directory has chapter files the CSV doesn't
+    /// list, which may belong to something else
+    pub force_cleanup: bool,
+
+    /// Print a line as each chapter starts, finishes or is skipped (always on with `verbose`)
+    pub per_chapter_logging: bool,
+
+    /// Candidates for the chapter text, each with its own extraction settings, tried in order
+    /// until one yields valid content. Replaces `selector` when set.
+    pub selectors: Vec<SelectorSpec>,
+
+    /// Chapter files (and database rows) written at once, independently of
+    /// `max_concurrent_tasks`, for slow storage
+    pub max_concurrent_writes: usize,
+}
+
+/// An entry of `selectors`, e.g. `{ selector = "#chapter", skip_text_nodes = 0, exclude = [".ad"] }`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SelectorSpec {
+    /// CSS selector of the content element (comma-separated alternatives allowed)
+    pub selector: String,
+
+    /// Initial text nodes to skip; `skip_text_nodes` when unset
+    #[serde(default)]
+    pub skip_text_nodes: Option<usize>,
+
+    /// CSS selectors of elements inside the content element to leave out, like ads or notes
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+/// Layout of scraped chapters inside the output directory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputLayout {
+    /// One `chapter_{number}.txt` file per chapter (or the configured `output_extension`)
+    #[default]
+    Flat,
+    /// One `chapter_{number}/` folder per chapter with `index.txt` and downloaded images in `assets/`
+    Bundle,
+}
+
+/// Handling of chapters whose file already exists
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExistingPolicy {
+    /// Leave existing chapters alone without fetching them
+    #[default]
+    Skip,
+    /// Fetch existing chapters again and replace them only when the new content is larger
+    OverwriteSmaller,
+    /// Fetch existing chapters again, replace the ones whose content changed and report them
+    Refresh,
+}
+
+/// HTTP authentication, written as `auth = { type = "basic", user = "...", pass = "..." }`
+/// or `auth = { type = "bearer", token = "..." }`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Auth {
+    Basic {
+        user: String,
+        #[serde(default)]
+        pass: Option<String>,
+    },
+    Bearer {
+        token: String,
+    },
+}
+
+/// Handling of pages whose content element has no text left after filtering
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmptyContentPolicy {
+    /// Fail the chapter with a content extraction error
+    #[default]
+    Error,
+    /// Write nothing and count the chapter as skipped rather than failed
+    Skip,
 }
 
 impl Default for ScrapingConfig {
@@ -79,6 +362,164 @@ impl Default for ScrapingConfig {
             
             // Keep verbose false for clean output by default
             verbose: false,
+
+            // A PID/timestamp suffix is appended so concurrent runs never share a probe. Not a
+            // dotfile: sync tools treat hidden files appearing as something to act on
+            write_probe_prefix: "scrapper_write_probe".to_string(),
+            skip_write_check: false,
+
+            // Flat files keep the output compatible with earlier runs
+            output_layout: OutputLayout::Flat,
+
+            // HTML dumps are opt-in and capped so a wholesale failure can't fill the disk
+            dump_html_on_failure: None,
+            max_html_dumps: 20,
+            max_html_dump_bytes: 2 * 1024 * 1024,
+
+            // Text rejected as too short is discarded unless asked to keep it
+            save_partial_on_failure: None,
+
+            // Per-chapter retry limits still apply when no run-wide budget is set
+            max_total_retries: None,
+
+            // Local `file://` pages must be opted into explicitly
+            allowed_schemes: DEFAULT_ALLOWED_SCHEMES.iter().map(|s| s.to_string()).collect(),
+
+            // No host restrictions unless configured
+            allowed_hosts: Vec::new(),
+            blocked_hosts: Vec::new(),
+
+            // Certificates are fully verified unless explicitly relaxed
+            danger_accept_invalid_certs: false,
+            root_cert: None,
+
+            // No output limits unless configured
+            max_output_files: None,
+            max_output_bytes: None,
+
+            // Header sidecars are only useful when debugging or archiving
+            save_headers: false,
+
+            // WARC archiving is opt-in, on top of regular extraction
+            warc_file: None,
+
+            // Retries usually mean the server was struggling, so they run one at a time by default
+            retry_concurrency: 1,
+
+            // Human-readable progress bars unless a supervising tool asks for JSON
+            progress_json: false,
+
+            // Chapters keep the `.txt` extension unless overridden
+            output_extension: None,
+
+            // Never abort on a low success rate unless asked; when asked, judge only after a fair sample
+            abort_below_success_rate: None,
+            abort_min_samples: 20,
+
+            // Well under the 255-byte name limit of common filesystems
+            max_filename_len: 200,
+
+            // Hidden content is only needed for a few anti-scraping sites
+            extract_from_comments: false,
+            include_noscript: false,
+
+            // A 404 is final unless the site has an alternative URL scheme
+            fallback_url_template: None,
+
+            // One line per chapter gets busy at high concurrency, so it's opt-in
+            chapter_progress: false,
+
+            // Existing chapters are never touched unless asked; replacements must be 10% larger
+            existing_policy: ExistingPolicy::Skip,
+            overwrite_min_growth: 1.1,
+
+            // Plain HTTP is far lighter; a browser is only needed for client-rendered sites
+            render: false,
+
+            // Content fetched by the page's own scripts usually lands within a few seconds
+            wait_for_selector: None,
+            wait_timeout_ms: 10_000,
+            render_settle_ms: 0,
+
+            // Every chapter is scraped unless sampling for a quality check
+            sample: None,
+            sample_seed: None,
+            only_chapters: Vec::new(),
+            exclude_file: None,
+
+            // Plain text files unless a database is asked for
+            sqlite_database: None,
+
+            // Chapters stay in separate files unless volumes are asked for
+            merge_chapters_per_volume: None,
+
+            // reqwest sends no Accept-Language, leaving the language up to the site
+            accept_language: None,
+
+            // Pages are requested anonymously unless credentials are configured
+            auth: None,
+            cookies_file: None,
+
+            // Trust the charset each page declares, and don't second-guess the decoded text
+            force_encoding: None,
+            detect_encoding_errors: false,
+
+            // Block and error pages commonly saved in place of a chapter
+            error_page_markers: vec![
+                "Access Denied".to_string(),
+                "403 Forbidden".to_string(),
+                "404 Not Found".to_string(),
+                "Just a moment...".to_string(),   // Cloudflare challenge
+                "Checking your browser".to_string(),
+                "Enable JavaScript and cookies to continue".to_string(),
+            ],
+
+            // Empty content usually means a wrong selector, so it fails unless told otherwise
+            empty_content_policy: EmptyContentPolicy::Error,
+
+            // No per-host limit by default
+            max_runtime_per_host_secs: None,
+
+            // Refreshing replaces changed chapters in place unless asked to keep backups
+            refresh_keep_backup: false,
+
+            // Only concurrency and delays pace the run unless a start rate is set
+            max_starts_per_sec: None,
+
+            profile: false,
+
+            // Chapter numbers come from the CSV unless a pattern is given
+            chapter_from_url_regex: None,
+            chapter_from_url_always: false,
+            strip_chapter_leading_zeros: true,
+
+            // A malformed row usually means a broken file, so stop unless told otherwise
+            skip_invalid_rows: false,
+
+            // Tracking parameters only matter once turned on, and the original URL is still
+            // requested in case a site needs it
+            canonicalize_urls: false,
+            tracking_params: DEFAULT_TRACKING_PARAMS.iter().map(|s| s.to_string()).collect(),
+            strip_on_request: false,
+
+            // skip_text_nodes and filter_patterns usually cover the page's navigation
+            trim_leading_lines: 0,
+            trim_trailing_lines: 0,
+
+            // Cleanup is cheap for most output directories; huge ones stop holding up the run
+            // after half a minute
+            cleanup: true,
+            cleanup_timeout_secs: 30,
+            force_cleanup: false,
+
+            // Large runs would flood the terminal; the progress bars and summary are enough
+            per_chapter_logging: false,
+
+            // A single `selector` covers most sites
+            selectors: Vec::new(),
+
+            // As many as chapters can be in flight, so writes only queue when lowered
+            max_concurrent_writes: 50,
         }
     }
 }
@@ -111,6 +552,8 @@ impl ScrapingConfig {
         let mut config = if let Some(config_path) = &args.config {
             match Self::from_file(config_path).await {
                 Ok(config) => config,
+                // A broken config file stops the run instead of silently using defaults
+                Err(e) if args.strict_config => return Err(e),
                 Err(e) => {
                     eprintln!("Warning: {}", e.user_friendly_message());
                     eprintln!("Using default configuration");
@@ -129,6 +572,8 @@ impl ScrapingConfig {
             config.output_dir = output;
         }
         if let Some(selector) = args.selector {
+            // An explicit selector wins over the candidates from the config file
+            config.selectors.clear();
             config.selector = selector;
         }
         if let Some(concurrent) = args.concurrent {
@@ -137,9 +582,81 @@ impl ScrapingConfig {
         if let Some(delay) = args.delay {
             config.task_delay_ms = delay;
         }
+        if let Some(sample) = args.sample {
+            config.sample = Some(sample);
+        }
+        if let Some(sample_seed) = args.sample_seed {
+            config.sample_seed = Some(sample_seed);
+        }
+        if !args.only.is_empty() {
+            config.only_chapters = args.only;
+        }
+        if let Some(exclude_file) = args.exclude_file {
+            config.exclude_file = Some(exclude_file);
+        }
+        if let Some(max_output_files) = args.max_output_files {
+            config.max_output_files = Some(max_output_files);
+        }
+        if let Some(max_output_bytes) = args.max_output_bytes {
+            config.max_output_bytes = Some(max_output_bytes);
+        }
+        if let Some(merge) = args.merge {
+            config.merge_chapters_per_volume = Some(merge);
+        }
+        if let Some(sqlite) = args.sqlite {
+            config.sqlite_database = Some(sqlite);
+        }
+        if let Some(warc) = args.warc {
+            config.warc_file = Some(warc);
+        }
+        if let Some(cookies) = args.cookies {
+            config.cookies_file = Some(cookies);
+        }
+        if let Some(max_total_retries) = args.max_total_retries {
+            config.max_total_retries = Some(max_total_retries);
+        }
+        if let Some(pattern) = args.chapter_from_url {
+            config.chapter_from_url_regex = Some(pattern);
+        }
+        if let Some(max_starts_per_sec) = args.max_starts_per_sec {
+            config.max_starts_per_sec = Some(max_starts_per_sec);
+        }
+        if let Some(max_runtime_per_host) = args.max_runtime_per_host {
+            config.max_runtime_per_host_secs = Some(max_runtime_per_host);
+        }
         if args.verbose {
             config.verbose = true;
         }
+        if args.profile {
+            config.profile = true;
+        }
+        if args.skip_invalid_rows {
+            config.skip_invalid_rows = true;
+        }
+        if args.skip_cleanup {
+            config.cleanup = false;
+        }
+        if args.force {
+            config.force_cleanup = true;
+        }
+        if args.log_chapters {
+            config.per_chapter_logging = true;
+        }
+        if args.progress_json {
+            config.progress_json = true;
+        }
+        if args.refresh {
+            config.existing_policy = ExistingPolicy::Refresh;
+        }
+        if args.keep_backup {
+            config.refresh_keep_backup = true;
+        }
+        if args.overwrite_smaller {
+            config.existing_policy = ExistingPolicy::OverwriteSmaller;
+        }
+        if args.render {
+            config.render = true;
+        }
 
         config.validate()?;
         Ok(config)
@@ -179,6 +696,229 @@ impl ScrapingConfig {
             ));
         }
         
+        if self.max_concurrent_writes == 0 {
+            return Err(ScrapperError::validation(
+                "max_concurrent_writes",
+                "must be greater than 0"
+            ));
+        }
+        
+        if self.retry_concurrency == 0 || self.retry_concurrency > self.max_concurrent_tasks {
+            return Err(ScrapperError::validation(
+                "retry_concurrency",
+                format!(
+                    "must be between 1 and max_concurrent_tasks ({})",
+                    self.max_concurrent_tasks
+                )
+            ));
+        }
+
+        if let Some(extension) = &self.output_extension {
+            let extension = extension.trim_start_matches('.');
+            if extension.is_empty()
+                || extension.len() > 16
+                || !extension.chars().all(|c| c.is_ascii_alphanumeric())
+            {
+                return Err(ScrapperError::validation(
+                    "output_extension",
+                    format!("'{extension}' is not a valid file extension (use 1-16 letters or digits, e.g. \"md\")")
+                ));
+            }
+        }
+
+        if let Some(rate) = self.abort_below_success_rate
+            && !(0.0..=100.0).contains(&rate)
+        {
+            return Err(ScrapperError::validation(
+                "abort_below_success_rate",
+                "must be a percentage between 0 and 100"
+            ));
+        }
+
+        if self.abort_min_samples == 0 {
+            return Err(ScrapperError::validation(
+                "abort_min_samples",
+                "must be greater than 0"
+            ));
+        }
+
+        if !(32..=255).contains(&self.max_filename_len) {
+            return Err(ScrapperError::validation(
+                "max_filename_len",
+                "must be between 32 and 255 bytes"
+            ));
+        }
+
+        if let Some(template) = &self.fallback_url_template {
+            if !template.contains(FALLBACK_CHAPTER_PLACEHOLDER) {
+                return Err(ScrapperError::validation(
+                    "fallback_url_template",
+                    format!("'{template}' must contain the {FALLBACK_CHAPTER_PLACEHOLDER} placeholder")
+                ));
+            }
+            urls::validate_url(&urls::fallback_url(template, "1"), &self.allowed_schemes)
+                .map_err(|message| ScrapperError::validation("fallback_url_template", message))?;
+        }
+
+        if self.overwrite_min_growth.is_nan() || self.overwrite_min_growth < 1.0 {
+            return Err(ScrapperError::validation(
+                "overwrite_min_growth",
+                "must be at least 1.0 (new content at least as large as the existing file)"
+            ));
+        }
+
+        if self.refresh_keep_backup && self.existing_policy != ExistingPolicy::Refresh {
+            return Err(ScrapperError::validation(
+                "refresh_keep_backup",
+                "only applies with existing_policy = \"refresh\" (--refresh)"
+            ));
+        }
+
+        if self.render && !cfg!(feature = "render") {
+            return Err(ScrapperError::validation(
+                "render",
+                "this build has no browser support; rebuild with `cargo build --release --features render`"
+            ));
+        }
+
+        if let Some(selector) = &self.wait_for_selector
+            && let Err(e) = scraper::Selector::parse(selector)
+        {
+            return Err(ScrapperError::validation(
+                "wait_for_selector",
+                format!("Invalid CSS selector '{selector}': {e:?}")
+            ));
+        }
+
+        if self.sqlite_database.is_some() {
+            if !cfg!(feature = "sqlite") {
+                return Err(ScrapperError::validation(
+                    "sqlite_database",
+                    "this build has no SQLite support; rebuild with `cargo build --release --features sqlite`"
+                ));
+            }
+            if self.existing_policy != ExistingPolicy::Skip {
+                return Err(ScrapperError::validation(
+                    "existing_policy",
+                    "only \"skip\" is supported when storing chapters in sqlite_database"
+                ));
+            }
+            if self.save_headers {
+                return Err(ScrapperError::validation(
+                    "save_headers",
+                    "headers sidecar files aren't written with sqlite_database"
+                ));
+            }
+            if self.output_layout == OutputLayout::Bundle {
+                return Err(ScrapperError::validation(
+                    "output_layout",
+                    "\"bundle\" directories (and their images) aren't written with sqlite_database"
+                ));
+            }
+        }
+
+        if let Some(chapters_per_volume) = self.merge_chapters_per_volume {
+            if chapters_per_volume == 0 {
+                return Err(ScrapperError::validation(
+                    "merge_chapters_per_volume",
+                    "must be at least 1 chapter per volume"
+                ));
+            }
+            if self.sqlite_database.is_some() {
+                return Err(ScrapperError::validation(
+                    "merge_chapters_per_volume",
+                    "volumes are merged from chapter files, which aren't written with sqlite_database"
+                ));
+            }
+        }
+
+        if self.sample == Some(0) {
+            return Err(ScrapperError::validation(
+                "sample",
+                "must be at least 1 chapter"
+            ));
+        }
+
+        if !self.only_chapters.is_empty() && self.sample.is_some() {
+            return Err(ScrapperError::validation(
+                "only_chapters",
+                "can't be combined with sample"
+            ));
+        }
+
+        if self.cleanup_timeout_secs == 0 {
+            return Err(ScrapperError::validation(
+                "cleanup_timeout_secs",
+                "must be at least 1 second; use cleanup = false to skip cleanup"
+            ));
+        }
+
+        if self.wait_timeout_ms == 0 {
+            return Err(ScrapperError::validation(
+                "wait_timeout_ms",
+                "must be greater than 0"
+            ));
+        }
+
+        match &self.auth {
+            Some(Auth::Basic { user, .. }) if user.is_empty() => {
+                return Err(ScrapperError::validation(
+                    "auth",
+                    "basic auth needs a non-empty `user`"
+                ));
+            }
+            Some(Auth::Bearer { token }) if token.trim().is_empty() => {
+                return Err(ScrapperError::validation(
+                    "auth",
+                    "bearer auth needs a non-empty `token`"
+                ));
+            }
+            _ => {}
+        }
+
+        if let Some(label) = &self.force_encoding
+            && encoding_rs::Encoding::for_label(label.trim().as_bytes()).is_none() {
+            return Err(ScrapperError::validation(
+                "force_encoding",
+                format!("unknown encoding '{label}', expected a label like \"windows-1252\" or \"shift_jis\"")
+            ));
+        }
+
+        if self.strip_on_request && !self.canonicalize_urls {
+            return Err(ScrapperError::validation(
+                "strip_on_request",
+                "only applies with canonicalize_urls = true"
+            ));
+        }
+
+        if let Some(pattern) = &self.chapter_from_url_regex {
+            let regex = regex::Regex::new(pattern).map_err(|e| {
+                ScrapperError::validation("chapter_from_url_regex", format!("invalid regex: {e}"))
+            })?;
+            if regex.captures_len() < 2 {
+                return Err(ScrapperError::validation(
+                    "chapter_from_url_regex",
+                    "needs a capture group around the chapter number, e.g. \"chapter-(\\d+)\""
+                ));
+            }
+        }
+
+        if let Some(rate) = self.max_starts_per_sec
+            && !(rate.is_finite() && rate > 0.0)
+        {
+            return Err(ScrapperError::validation(
+                "max_starts_per_sec",
+                "must be a positive number"
+            ));
+        }
+
+        if self.max_runtime_per_host_secs == Some(0) {
+            return Err(ScrapperError::validation(
+                "max_runtime_per_host_secs",
+                "must be greater than 0"
+            ));
+        }
+
         // Add minimum delay validation
         if self.task_delay_ms < 50 {
             return Err(ScrapperError::validation(
@@ -193,6 +933,13 @@ impl ScrapingConfig {
                 "cannot be empty"
             ));
         }
+
+        if self.selectors.iter().any(|spec| spec.selector.trim().is_empty()) {
+            return Err(ScrapperError::validation(
+                "selectors",
+                "every entry needs a non-empty `selector`"
+            ));
+        }
         
         if self.request_timeout_secs == 0 {
             return Err(ScrapperError::validation(
@@ -209,9 +956,83 @@ impl ScrapingConfig {
             ));
         }
 
-        // Validate file paths exist for input
-        if !self.input_file.exists() {
-            eprintln!("⚠️  Warning: Input file {:?} does not exist", self.input_file);
+        if self.write_probe_prefix.trim().is_empty()
+            || self.write_probe_prefix.contains(['/', '\\'])
+        {
+            return Err(ScrapperError::validation(
+                "write_probe_prefix",
+                "must be a non-empty file name without path separators",
+            ));
+        }
+
+        if self.allowed_schemes.is_empty() {
+            return Err(ScrapperError::validation(
+                "allowed_schemes",
+                "must contain at least one scheme",
+            ));
+        }
+
+        if let Some(scheme) = self.allowed_schemes.iter().find(|scheme| {
+            !SUPPORTED_SCHEMES.contains(&scheme.to_ascii_lowercase().as_str())
+        }) {
+            return Err(ScrapperError::validation(
+                "allowed_schemes",
+                format!(
+                    "unsupported scheme '{scheme}'; supported schemes are: {}",
+                    SUPPORTED_SCHEMES.join(", ")
+                ),
+            ));
+        }
+
+        for pattern in &self.allowed_hosts {
+            urls::validate_host_pattern(pattern)
+                .map_err(|message| ScrapperError::validation("allowed_hosts", message))?;
+        }
+
+        for pattern in &self.blocked_hosts {
+            urls::validate_host_pattern(pattern)
+                .map_err(|message| ScrapperError::validation("blocked_hosts", message))?;
+        }
+
+        if let Some(root_cert) = &self.root_cert
+            && !root_cert.exists()
+        {
+            return Err(ScrapperError::validation(
+                "root_cert",
+                format!("certificate file {root_cert:?} does not exist"),
+            ));
+        }
+
+        if self.save_partial_on_failure.as_ref() == Some(&self.output_dir) {
+            return Err(ScrapperError::validation(
+                "save_partial_on_failure",
+                "must differ from output_dir, or partial chapters would count as scraped"
+            ));
+        }
+
+        if let Some(exclude_file) = &self.exclude_file
+            && !exclude_file.exists()
+        {
+            return Err(ScrapperError::validation(
+                "exclude_file",
+                format!("exclude file {exclude_file:?} does not exist"),
+            ));
+        }
+
+        if let Some(cookies_file) = &self.cookies_file
+            && !cookies_file.exists()
+        {
+            return Err(ScrapperError::validation(
+                "cookies_file",
+                format!("cookies file {cookies_file:?} does not exist"),
+            ));
+        }
+
+        if self.max_output_files == Some(0) || self.max_output_bytes == Some(0) {
+            return Err(ScrapperError::validation(
+                "max_output_files/max_output_bytes",
+                "must be greater than 0 when set",
+            ));
         }
 
         Ok(())
@@ -231,7 +1052,7 @@ struct Args {
     #[arg(short, long)]
     config: Option<PathBuf>,
 
-    /// Input CSV file path
+    /// Input CSV file path, or an http(s) URL to download it from
     #[arg(short, long)]
     input: Option<PathBuf>,
 
@@ -251,13 +1072,179 @@ struct Args {
     #[arg(long)]
     delay: Option<u64>,
 
+    /// Scrape a random sample of N chapters spread across the CSV, for spot-checking extraction
+    #[arg(long, value_name = "N")]
+    sample: Option<usize>,
+
+    /// Seed for --sample, to pick the same chapters again
+    #[arg(long, value_name = "SEED")]
+    sample_seed: Option<u64>,
+
+    /// Scrape only these chapters (e.g. `12,45,server-intro`), replacing their existing files
+    #[arg(long, value_name = "CHAPTERS", value_delimiter = ',', conflicts_with = "sample")]
+    only: Vec<String>,
+
+    /// Leave out the chapters listed in this CSV, e.g. the verify_failures.csv of an earlier run
+    #[arg(long, value_name = "PATH")]
+    exclude_file: Option<PathBuf>,
+
+    /// Stop after writing this many chapter files
+    #[arg(long)]
+    max_output_files: Option<usize>,
+
+    /// Stop after writing this many bytes
+    #[arg(long)]
+    max_output_bytes: Option<u64>,
+
+    /// After scraping, merge chapters into volume_N files of this many chapters each
+    #[arg(long, value_name = "CHAPTERS_PER_VOLUME")]
+    merge: Option<usize>,
+
+    /// Store chapters in this SQLite database instead of text files (builds with the `sqlite` feature only)
+    #[arg(long, value_name = "PATH")]
+    sqlite: Option<PathBuf>,
+
+    /// Also archive fetched pages (request and raw response) to this WARC file
+    #[arg(long, value_name = "PATH")]
+    warc: Option<PathBuf>,
+
+    /// Send the cookies in this Netscape cookies.txt file, e.g. exported from a logged-in browser
+    #[arg(long, value_name = "PATH")]
+    cookies: Option<PathBuf>,
+
+    /// Maximum retry attempts across the whole run
+    #[arg(long)]
+    max_total_retries: Option<usize>,
+
+    /// Regex whose first capture group gives the chapter number from the URL when the CSV has none
+    #[arg(long, value_name = "REGEX")]
+    chapter_from_url: Option<String>,
+
+    /// Start at most this many chapters per second
+    #[arg(long, value_name = "RATE")]
+    max_starts_per_sec: Option<f64>,
+
+    /// Seconds to spend scraping any one host; its remaining chapters are written to remaining_links.csv
+    #[arg(long, value_name = "SECS")]
+    max_runtime_per_host: Option<u64>,
+
     /// Enable verbose output
     #[arg(short, long)]
     verbose: bool,
 
+    /// Re-scrape existing chapters, replacing them only when the new content is larger
+    #[arg(long)]
+    overwrite_smaller: bool,
+
+    /// Re-scrape existing chapters, replace the ones that changed and list them
+    #[arg(long, conflicts_with = "overwrite_smaller")]
+    refresh: bool,
+
+    /// With --refresh, keep the previous version of each changed chapter as <file>.bak
+    #[arg(long)]
+    keep_backup: bool,
+
+    /// Emit one JSON object per progress event on stderr instead of progress bars
+    #[arg(long)]
+    progress_json: bool,
+
+    /// Print a timing breakdown of the run phases at the end
+    #[arg(long)]
+    profile: bool,
+
+    /// Skip malformed CSV rows instead of stopping at the first one
+    #[arg(long)]
+    skip_invalid_rows: bool,
+
+    /// Don't remove empty or tiny chapter files from earlier runs before scraping
+    #[arg(long)]
+    skip_cleanup: bool,
+
+    /// Clean up even when the output directory has chapter files the CSV doesn't list
+    #[arg(long, conflicts_with = "skip_cleanup")]
+    force: bool,
+
+    /// Print a line as each chapter starts, finishes or is skipped
+    #[arg(long)]
+    log_chapters: bool,
+
+    /// Load pages in a headless browser (builds with the `render` feature only)
+    #[arg(long)]
+    render: bool,
+
+    /// Fail instead of falling back to defaults when the config file can't be loaded
+    #[arg(long)]
+    strict_config: bool,
+
     /// Generate sample configuration file
     #[arg(long)]
     generate_config: Option<PathBuf>,
+
+    /// Crawl a table-of-contents URL and write its chapter links as a CSV instead of scraping
+    #[arg(long, value_name = "TOC_URL")]
+    discover: Option<String>,
+
+    /// CSS selector for chapter links on table-of-contents pages (with --discover)
+    #[arg(long, requires = "discover")]
+    link_selector: Option<String>,
+
+    /// CSS selector for the "next page" link of a paginated table of contents (with --discover)
+    #[arg(long, requires = "discover")]
+    next_selector: Option<String>,
+
+    /// Where to write the discovered links CSV (with --discover; defaults to the input file,
+    /// which is only overwritten when named here)
+    #[arg(long, requires = "discover")]
+    discover_output: Option<PathBuf>,
+
+    /// Check the output of earlier runs against the CSV without fetching anything, listing
+    /// chapters to scrape again
+    #[arg(long, conflicts_with = "discover")]
+    verify: bool,
+}
+
+/// Default selector for chapter links on a table-of-contents page
+const DEFAULT_LINK_SELECTOR: &str = "a[href]";
+
+/// Default selector for the "next page" link of a paginated table of contents
+const DEFAULT_NEXT_SELECTOR: &str = "a[rel~=next], .next a, a.next, .pagination .next";
+
+/// What to crawl when running with `--discover`
+#[derive(Debug, Clone)]
+pub struct DiscoverOptions {
+    /// First table-of-contents page
+    pub toc_url: String,
+    /// Selector matching chapter links
+    pub link_selector: String,
+    /// Selector matching the link to the next table-of-contents page
+    pub next_selector: String,
+    /// CSV to write; the configured input file when unset
+    pub output: Option<PathBuf>,
+}
+
+/// Return the discovery options if the scraper was started with `--discover`
+pub fn discover_options() -> Option<DiscoverOptions> {
+    use clap::Parser;
+
+    let args = Args::parse();
+
+    args.discover.map(|toc_url| DiscoverOptions {
+        toc_url,
+        link_selector: args
+            .link_selector
+            .unwrap_or_else(|| DEFAULT_LINK_SELECTOR.to_string()),
+        next_selector: args
+            .next_selector
+            .unwrap_or_else(|| DEFAULT_NEXT_SELECTOR.to_string()),
+        output: args.discover_output,
+    })
+}
+
+/// Whether the scraper was started with `--verify`
+pub fn verify_requested() -> bool {
+    use clap::Parser;
+
+    Args::parse().verify
 }
 
 pub async fn handle_config_generation() -> ScrapperResult<bool> {
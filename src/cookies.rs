@@ -0,0 +1,204 @@
+//! `cookies_file`: loads cookies exported from a browser in the Netscape `cookies.txt` format,
+//! so pages are fetched with the browser's logged-in session.
+
+use crate::error::{ScrapperError, ScrapperResult};
+use reqwest::cookie::Jar;
+use std::path::Path;
+use std::sync::Arc;
+use url::Url;
+
+/// Prefix browsers put before the domain of `HttpOnly` cookies, turning the line into a comment
+/// for older parsers
+const HTTP_ONLY_PREFIX: &str = "#HttpOnly_";
+
+/// One line of a `cookies.txt` file
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetscapeCookie {
+    pub domain: String,
+    pub include_subdomains: bool,
+    pub path: String,
+    pub secure: bool,
+    /// Unix timestamp, `0` for session cookies
+    pub expires: i64,
+    pub name: String,
+    pub value: String,
+}
+
+impl NetscapeCookie {
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires != 0 && self.expires < now
+    }
+
+    /// The URL the cookie is set from, so the jar scopes it to the right host
+    fn origin(&self) -> Option<Url> {
+        let scheme = if self.secure { "https" } else { "http" };
+        let host = self.domain.trim_start_matches('.');
+        Url::parse(&format!("{scheme}://{host}{}", self.path)).ok()
+    }
+
+    /// The cookie as a `Set-Cookie` header value
+    fn set_cookie(&self) -> String {
+        let mut header = format!("{}={}; Path={}", self.name, self.value, self.path);
+        // Without a Domain attribute the cookie is only sent to the exact host
+        if self.include_subdomains {
+            header.push_str(&format!("; Domain={}", self.domain.trim_start_matches('.')));
+        }
+        if self.secure {
+            header.push_str("; Secure");
+        }
+        header
+    }
+}
+
+fn parse_flag(value: &str, line: usize, field: &str) -> Result<bool, String> {
+    match value.to_ascii_uppercase().as_str() {
+        "TRUE" => Ok(true),
+        "FALSE" => Ok(false),
+        _ => Err(format!(
+            "line {line}: {field} must be TRUE or FALSE, got '{value}'"
+        )),
+    }
+}
+
+/// Parse a Netscape `cookies.txt` file: one cookie per line with 7 tab-separated fields
+/// (domain, include subdomains, path, secure, expiry, name, value)
+pub fn parse(contents: &str) -> Result<Vec<NetscapeCookie>, String> {
+    let mut cookies = Vec::new();
+
+    for (index, line) in contents.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.strip_prefix(HTTP_ONLY_PREFIX).unwrap_or(line);
+        if line.trim().is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 7 {
+            return Err(format!(
+                "line {line_number}: expected 7 tab-separated fields, found {}",
+                fields.len()
+            ));
+        }
+
+        let (domain, path, name) = (fields[0], fields[2], fields[5]);
+        if domain.is_empty() || name.is_empty() {
+            return Err(format!(
+                "line {line_number}: domain and cookie name can't be empty"
+            ));
+        }
+
+        let expires = fields[4].parse().map_err(|_| {
+            format!(
+                "line {line_number}: expiry must be a Unix timestamp, got '{}'",
+                fields[4]
+            )
+        })?;
+
+        cookies.push(NetscapeCookie {
+            domain: domain.to_string(),
+            include_subdomains: parse_flag(fields[1], line_number, "include subdomains")?,
+            path: if path.is_empty() { "/" } else { path }.to_string(),
+            secure: parse_flag(fields[3], line_number, "secure")?,
+            expires,
+            name: name.to_string(),
+            value: fields[6].trim_end_matches('\r').to_string(),
+        });
+    }
+
+    Ok(cookies)
+}
+
+/// Load a `cookies.txt` file into a cookie jar, leaving out (and warning about) expired cookies
+pub fn load_jar(path: &Path) -> ScrapperResult<Arc<Jar>> {
+    let contents = std::fs::read_to_string(path).map_err(|e| {
+        ScrapperError::file_system(
+            format!("Failed to read cookies file: {e}"),
+            Some(path.to_path_buf()),
+        )
+    })?;
+
+    let cookies = parse(&contents).map_err(|message| {
+        ScrapperError::validation(
+            "cookies_file",
+            format!("{path:?} is not a Netscape cookies.txt file: {message}"),
+        )
+    })?;
+
+    let now = chrono::Utc::now().timestamp();
+    let (expired, current): (Vec<_>, Vec<_>) = cookies
+        .into_iter()
+        .partition(|cookie| cookie.is_expired(now));
+
+    if !expired.is_empty() {
+        let names: Vec<String> = expired
+            .iter()
+            .map(|cookie| format!("{} ({})", cookie.name, cookie.domain))
+            .collect();
+        eprintln!(
+            "⚠️  {} expired cookies in {path:?} were left out: {}",
+            expired.len(),
+            names.join(", ")
+        );
+        eprintln!("💡 Export the cookies again if the site asks you to log in");
+    }
+
+    let jar = Jar::default();
+    for cookie in &current {
+        if let Some(origin) = cookie.origin() {
+            jar.add_cookie_str(&cookie.set_cookie(), &origin);
+        }
+    }
+
+    Ok(Arc::new(jar))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TempDir;
+    use reqwest::cookie::CookieStore;
+
+    const COOKIES_TXT: &str = "# Netscape HTTP Cookie File\n\
+        \n\
+        .example.com\tTRUE\t/\tFALSE\t0\tsession\tabc123\n\
+        #HttpOnly_books.example.com\tFALSE\t/read\tTRUE\t4102444800\ttoken\tsecret\n\
+        .example.com\tTRUE\t/\tFALSE\t946684800\told\tstale\n";
+
+    #[test]
+    fn test_parse_cookies_txt() {
+        let cookies = parse(COOKIES_TXT).unwrap();
+
+        assert_eq!(cookies.len(), 3);
+        assert_eq!(cookies[1].domain, "books.example.com");
+        assert!(cookies[1].secure && !cookies[1].include_subdomains);
+        assert!(!cookies[0].is_expired(1_700_000_000));
+        assert!(cookies[2].is_expired(1_700_000_000));
+
+        let error = parse(".example.com\tTRUE\t/\n").unwrap_err();
+        assert!(error.starts_with("line 1:"));
+        let error = parse(".example.com\tyes\t/\tFALSE\t0\ta\tb\n").unwrap_err();
+        assert!(error.contains("TRUE or FALSE"));
+    }
+
+    #[tokio::test]
+    async fn test_load_jar_scopes_cookies_and_skips_expired() {
+        let dir = TempDir::new("cookies");
+        let path = dir.join("cookies.txt");
+        tokio::fs::write(&path, COOKIES_TXT).await.unwrap();
+        let jar = load_jar(&path).unwrap();
+
+        let cookies_for = |url: &str| {
+            jar.cookies(&Url::parse(url).unwrap())
+                .map(|value| value.to_str().unwrap().to_string())
+                .unwrap_or_default()
+        };
+
+        assert_eq!(cookies_for("http://www.example.com/"), "session=abc123");
+        let reader = cookies_for("https://books.example.com/read/1");
+        assert!(reader.contains("token=secret") && reader.contains("session=abc123"));
+        assert!(!reader.contains("old=stale"));
+        // Host-only and secure: not sent to other hosts or over plain HTTP
+        assert!(!cookies_for("https://www.example.com/read/1").contains("token"));
+        assert!(!cookies_for("http://books.example.com/read/1").contains("token"));
+    }
+}
@@ -1,81 +1,310 @@
 use crate::error::{ScrapperError, ScrapperResult};
-use crate::types::{ChapterRecord, ScrapingStats};
-use csv_async::AsyncReader;
-use std::path::Path;
+use crate::file_manager::FileManager;
+use crate::types::{ChapterRecord, Config, ScrapingStats};
+use crate::urls;
+use crate::web_scraper;
+use csv_async::{AsyncReader, AsyncReaderBuilder, AsyncWriter, StringRecord};
+use rand::SeedableRng;
+use rand::rngs::ChaCha8Rng;
+use regex::Regex;
+use std::collections::HashSet;
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use tokio::fs::File;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio::sync::OnceCell;
 use tokio_stream::StreamExt;
 
+/// Byte order mark Excel and other tools put at the start of UTF-8 CSV exports
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// Content types a CSV link list may be served with: raw gist and pastebin links are
+/// `text/plain`, some servers don't know the extension at all
+const CSV_CONTENT_TYPES: &[&str] = &[
+    "text/csv",
+    "text/plain",
+    "application/csv",
+    "application/vnd.ms-excel",
+    "application/octet-stream",
+];
+
+/// Numbers the temp files of downloaded inputs, so readers in one process don't share one
+static DOWNLOADS: AtomicUsize = AtomicUsize::new(0);
+
+/// Whether `input_file` is an `http(s)://` URL rather than a local path
+pub fn is_url(path: &Path) -> bool {
+    path.to_str().is_some_and(|path| {
+        let lowercase = path.to_ascii_lowercase();
+        lowercase.starts_with("http://") || lowercase.starts_with("https://")
+    })
+}
+
+/// The download of an input given as a URL, kept in a temp file so every pass over the CSV
+/// reads the same copy. Removed when the reader is dropped.
+struct DownloadedInput {
+    url: String,
+    /// The run's client, so the CSV is fetched with the same credentials, cookies and TLS
+    /// settings as chapters. Built from `config` when the reader wasn't given one.
+    client: Option<reqwest::Client>,
+    config: Config,
+    path: OnceCell<PathBuf>,
+}
+
+impl DownloadedInput {
+    /// The temp file, downloading the CSV on first use
+    async fn path(&self) -> ScrapperResult<&Path> {
+        let path = self.path.get_or_try_init(|| self.download()).await?;
+        Ok(path)
+    }
+
+    async fn download(&self) -> ScrapperResult<PathBuf> {
+        let client = match &self.client {
+            Some(client) => client.clone(),
+            None => web_scraper::http_client(&self.config)?,
+        };
+
+        let response = client.get(&self.url).send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(ScrapperError::http(
+                &self.url,
+                Some(status.as_u16()),
+                format!("Failed to download the input CSV: HTTP {}", status.as_u16()),
+            ));
+        }
+
+        // A missing content type is given the benefit of the doubt; the CSV checks still apply
+        if let Some(content_type) = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+        {
+            let mime = content_type.split(';').next().unwrap_or("").trim();
+            if !CSV_CONTENT_TYPES
+                .iter()
+                .any(|allowed| mime.eq_ignore_ascii_case(allowed))
+            {
+                return Err(ScrapperError::csv(format!(
+                    "{} is served as '{mime}', not CSV. Link to the raw file instead (e.g. a gist's \"Raw\" button)",
+                    self.url
+                )));
+            }
+        }
+
+        let bytes = response.bytes().await?;
+        let path = std::env::temp_dir().join(format!(
+            "scrapper_input_{}_{}.csv",
+            std::process::id(),
+            DOWNLOADS.fetch_add(1, Ordering::Relaxed)
+        ));
+        tokio::fs::write(&path, &bytes).await.map_err(|e| {
+            ScrapperError::file_system(
+                format!("Failed to save the downloaded input CSV: {e}"),
+                Some(path.clone()),
+            )
+        })?;
+
+        Ok(path)
+    }
+}
+
+impl Drop for DownloadedInput {
+    fn drop(&mut self) {
+        if let Some(path) = self.path.get() {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
 pub struct CsvReader {
     file_path: std::path::PathBuf,
+    /// Set when `input_file` is a URL
+    download: Option<DownloadedInput>,
+    allowed_schemes: Vec<String>,
+    numbering: ChapterNumbering,
+    skip_invalid_rows: bool,
+    /// Query parameters ignored when comparing URLs, with `canonicalize_urls`
+    tracking_params: Option<Vec<String>>,
+    strip_on_request: bool,
+}
+
+/// Canonical URLs read so far, for leaving out rows whose URL only differs from an earlier one
+/// by tracking parameters
+struct SeenUrls<'a> {
+    tracking_params: Option<&'a [String]>,
+    seen: HashSet<String>,
+}
+
+impl SeenUrls<'_> {
+    fn is_duplicate(&mut self, url: &str) -> bool {
+        let Some(params) = self.tracking_params else {
+            return false;
+        };
+        !self.seen.insert(urls::canonical_url(url.trim(), params))
+    }
+}
+
+/// Where a record's chapter number comes from: the CSV column, or the URL with
+/// `chapter_from_url_regex`
+#[derive(Debug, Clone, Default)]
+pub struct ChapterNumbering {
+    regex: Option<Regex>,
+    always: bool,
+    strip_leading_zeros: bool,
+}
+
+impl ChapterNumbering {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            // The pattern was checked when the config was validated
+            regex: config
+                .chapter_from_url_regex
+                .as_deref()
+                .and_then(|pattern| Regex::new(pattern).ok()),
+            always: config.chapter_from_url_always,
+            strip_leading_zeros: config.strip_chapter_leading_zeros,
+        }
+    }
+
+    /// Whether chapter numbers can be taken from URLs, making the CSV column optional
+    pub fn uses_urls(&self) -> bool {
+        self.regex.is_some()
+    }
+
+    /// Chapter number captured from a URL by the first group of the regex
+    pub fn from_url(&self, url: &str) -> Option<String> {
+        let captured = self.regex.as_ref()?.captures(url)?.get(1)?.as_str().trim();
+        if captured.is_empty() {
+            return None;
+        }
+        Some(if self.strip_leading_zeros {
+            strip_leading_zeros(captured)
+        } else {
+            captured.to_string()
+        })
+    }
+
+    /// The chapter number of a row: its CSV field, unless that is empty or URLs always take
+    /// precedence. Empty when neither has one.
+    pub fn chapter_number(&self, url: &str, csv_field: &str) -> String {
+        let csv_field = csv_field.trim();
+        if !self.always && !csv_field.is_empty() {
+            return csv_field.to_string();
+        }
+        self.from_url(url).unwrap_or_else(|| csv_field.to_string())
+    }
+}
+
+/// `0012` becomes `12` and `007.5` becomes `7.5`, but `0` and `0.5` are kept
+fn strip_leading_zeros(number: &str) -> String {
+    let stripped = number.trim_start_matches('0');
+    if stripped.is_empty() || !stripped.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("0{stripped}")
+    } else {
+        stripped.to_string()
+    }
 }
 
 impl CsvReader {
-    pub fn new<P: AsRef<Path>>(file_path: P) -> Self {
+    pub fn new(config: &Config) -> Self {
         Self {
-            file_path: file_path.as_ref().to_path_buf(),
+            file_path: config.input_file.clone(),
+            download: is_url(&config.input_file).then(|| DownloadedInput {
+                url: config.input_file.to_string_lossy().into_owned(),
+                client: None,
+                config: config.clone(),
+                path: OnceCell::new(),
+            }),
+            allowed_schemes: config.allowed_schemes.clone(),
+            numbering: ChapterNumbering::new(config),
+            skip_invalid_rows: config.skip_invalid_rows,
+            tracking_params: config
+                .canonicalize_urls
+                .then(|| config.tracking_params.clone()),
+            strip_on_request: config.strip_on_request,
         }
     }
 
-    pub async fn read_records(&self) -> ScrapperResult<Vec<ChapterRecord>> {
-        let file = File::open(&self.file_path).await.map_err(|e| {
+    /// Download an input URL with `client`, the one the run fetches chapters with
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        if let Some(download) = &mut self.download {
+            download.client = Some(client);
+        }
+        self
+    }
+
+    fn seen_urls(&self) -> SeenUrls<'_> {
+        SeenUrls {
+            tracking_params: self.tracking_params.as_deref(),
+            seen: HashSet::new(),
+        }
+    }
+
+    /// Open the input file positioned after a UTF-8 byte order mark, which spreadsheet
+    /// exports often start with and which would otherwise stick to the first field. An input
+    /// URL is downloaded the first time.
+    async fn open_input(&self, purpose: &str) -> ScrapperResult<File> {
+        let file_path = match &self.download {
+            Some(download) => download.path().await?,
+            None => self.file_path.as_path(),
+        };
+        let open_error = |e: std::io::Error| {
+            let purpose = if purpose.is_empty() {
+                String::new()
+            } else {
+                format!(" {purpose}")
+            };
             ScrapperError::file_system(
-                format!("Failed to open CSV file: {e}"),
-                Some(self.file_path.clone()),
+                format!("Failed to open CSV file{purpose}: {e}"),
+                Some(file_path.to_path_buf()),
             )
-        })?;
+        };
 
-        let mut reader = AsyncReader::from_reader(file);
+        let mut file = File::open(file_path).await.map_err(open_error)?;
+        let mut head = Vec::with_capacity(UTF8_BOM.len());
+        (&mut file)
+            .take(UTF8_BOM.len() as u64)
+            .read_to_end(&mut head)
+            .await
+            .map_err(open_error)?;
+        if head != UTF8_BOM {
+            file.seek(SeekFrom::Start(0)).await.map_err(open_error)?;
+        }
+
+        Ok(file)
+    }
+
+    pub async fn read_records(&self) -> ScrapperResult<Vec<ChapterRecord>> {
+        let file = self.open_input("").await?;
+
+        let mut reader = flexible_reader(file);
         let mut records = reader.records();
         let mut chapter_records = Vec::new();
+        let mut seen_urls = self.seen_urls();
         let mut line_number = 1; // Track line number for better error reporting
 
         while let Some(record) = records.next().await {
-            let record = record.map_err(|e| {
-                ScrapperError::csv(format!(
+            let row = match record {
+                // Blank rows, typically trailing ones left by other tools, carry no link
+                Ok(record) if is_blank_row(&record) => {
+                    line_number += 1;
+                    continue;
+                }
+                Ok(record) => self.parse_row(&record, line_number),
+                Err(e) => Err(ScrapperError::csv(format!(
                     "Failed to read CSV record at line {line_number}: {e}"
-                ))
-            })?;
-
-            let url = record
-                .get(0)
-                .ok_or_else(|| {
-                    ScrapperError::csv(format!("Missing URL column in CSV at line {line_number}"))
-                })?
-                .trim()
-                .to_string();
-
-            let chapter_number = record
-                .get(1)
-                .ok_or_else(|| {
-                    ScrapperError::csv(format!(
-                        "Missing chapter number column in CSV at line {line_number}"
-                    ))
-                })?
-                .trim()
-                .to_string();
-
-            // Validate URL format
-            if url.is_empty() {
-                return Err(ScrapperError::csv(format!(
-                    "Empty URL at line {line_number}"
-                )));
-            }
+                ))),
+            };
 
-            // Basic URL validation
-            if !url.starts_with("http://") && !url.starts_with("https://") {
-                return Err(ScrapperError::csv(format!(
-                    "Invalid URL format at line {line_number}: '{url}'. URLs must start with http:// or https://"
-                )));
+            match row {
+                // The same chapter listed again with other tracking parameters
+                Ok(chapter) if seen_urls.is_duplicate(&chapter.url) => {}
+                Ok(chapter) => chapter_records.push(chapter),
+                // Reported by `get_stats`, which checks rows the same way
+                Err(_) if self.skip_invalid_rows => {}
+                Err(e) => return Err(e),
             }
-
-            // Validate chapter number
-            if chapter_number.is_empty() {
-                return Err(ScrapperError::csv(format!(
-                    "Empty chapter number at line {line_number}"
-                )));
-            }
-
-            chapter_records.push(ChapterRecord::new(url, chapter_number));
             line_number += 1;
         }
 
@@ -88,41 +317,122 @@ impl CsvReader {
         Ok(chapter_records)
     }
 
-    pub async fn count_records_and_existing<P: AsRef<Path>>(
+    /// Turn a non-blank row into a record, or say why it is invalid
+    fn parse_row(&self, record: &StringRecord, line_number: u64) -> ScrapperResult<ChapterRecord> {
+        let url = record
+            .get(0)
+            .ok_or_else(|| {
+                ScrapperError::csv(format!("Missing URL column in CSV at line {line_number}"))
+            })?
+            .trim()
+            .to_string();
+
+        let chapter_field = match record.get(1) {
+            Some(field) => field,
+            None if self.numbering.uses_urls() => "",
+            None => {
+                return Err(ScrapperError::csv(format!(
+                    "Missing chapter number column in CSV at line {line_number}"
+                )));
+            }
+        };
+        let chapter_number = self.numbering.chapter_number(&url, chapter_field);
+
+        // Validate URL format
+        if url.is_empty() {
+            return Err(ScrapperError::csv(format!(
+                "Empty URL at line {line_number}"
+            )));
+        }
+
+        // Basic URL validation
+        if let Err(message) = urls::validate_url(&url, &self.allowed_schemes) {
+            return Err(ScrapperError::csv(format!(
+                "{message} (line {line_number})"
+            )));
+        }
+
+        // Validate chapter number
+        if chapter_number.is_empty() {
+            let hint = if self.numbering.uses_urls() {
+                " (and chapter_from_url_regex found none in the URL)"
+            } else {
+                ""
+            };
+            return Err(ScrapperError::csv(format!(
+                "Empty chapter number at line {line_number}{hint}"
+            )));
+        }
+
+        // Request the canonical URL only when asked to; sites may need the original one
+        let url = match &self.tracking_params {
+            Some(params) if self.strip_on_request => urls::canonical_url(&url, params),
+            _ => url,
+        };
+
+        Ok(ChapterRecord::new(url, chapter_number))
+    }
+
+    pub async fn count_records_and_existing(
         &self,
-        output_dir: P,
+        file_manager: &FileManager,
     ) -> ScrapperResult<ScrapingStats> {
-        let file = File::open(&self.file_path).await.map_err(|e| {
-            ScrapperError::file_system(
-                format!("Failed to open CSV file for counting: {e}"),
-                Some(self.file_path.clone()),
-            )
-        })?;
+        let file = self.open_input("for counting").await?;
 
-        let mut reader = AsyncReader::from_reader(file);
+        let mut reader = flexible_reader(file);
         let mut records = reader.records();
         let mut stats = ScrapingStats::default();
+        let mut seen_urls = self.seen_urls();
         let mut line_number = 1;
 
         while let Some(record) = records.next().await {
-            let record = record.map_err(|e| {
-                ScrapperError::csv(format!(
-                    "Failed to read CSV record while counting at line {line_number}: {e}"
-                ))
-            })?;
+            let record = match record {
+                Ok(record) => record,
+                Err(_) if self.skip_invalid_rows => {
+                    line_number += 1;
+                    continue;
+                }
+                Err(e) => {
+                    return Err(ScrapperError::csv(format!(
+                        "Failed to read CSV record while counting at line {line_number}: {e}"
+                    )));
+                }
+            };
+
+            if is_blank_row(&record) {
+                line_number += 1;
+                continue;
+            }
+
+            // Rows `read_records` leaves out aren't chapters to process
+            if self.skip_invalid_rows
+                && row_warning(&record, &self.allowed_schemes, &self.numbering).is_some()
+            {
+                line_number += 1;
+                continue;
+            }
+
+            let url = record.get(0).unwrap_or("").trim();
+            if seen_urls.is_duplicate(url) {
+                line_number += 1;
+                continue;
+            }
 
             stats.total += 1;
 
-            if let Some(chapter_number) = record.get(1) {
-                let chapter_number = chapter_number.trim();
-                if !chapter_number.is_empty() {
-                    let file_path = output_dir
-                        .as_ref()
-                        .join(format!("chapter_{chapter_number}.txt"));
+            let chapter_number = self
+                .numbering
+                .chapter_number(url, record.get(1).unwrap_or(""));
+            if !chapter_number.is_empty() {
+                let chapter = ChapterRecord::new(url.to_string(), chapter_number);
 
-                    if file_path.exists() {
-                        stats.existing += 1;
-                    }
+                let saved_size = file_manager.saved_chapter_size(&chapter);
+                if let Some(size) = saved_size {
+                    stats.saved_files += 1;
+                    stats.saved_bytes += size;
+                }
+                if file_manager.should_skip_saved(&chapter, saved_size) {
+                    stats.existing += 1;
                 }
             }
 
@@ -134,22 +444,22 @@ impl CsvReader {
 
     /// Validate CSV file format without fully parsing it
     pub async fn validate_format(&self) -> ScrapperResult<()> {
-        let file = File::open(&self.file_path).await.map_err(|e| {
-            ScrapperError::file_system(
-                format!("Failed to open CSV file for validation: {e}"),
-                Some(self.file_path.clone()),
-            )
-        })?;
+        let file = self.open_input("for validation").await?;
 
         let mut reader = AsyncReader::from_reader(file);
 
-        // Check if we can read at least one record
+        // Check if we can read at least one record; with skip_invalid_rows, a bad first row is
+        // skipped like any other
         if let Some(record) = reader.records().next().await {
+            if self.skip_invalid_rows {
+                return Ok(());
+            }
+
             let record = record
                 .map_err(|e| ScrapperError::csv(format!("CSV format validation failed: {e}")))?;
 
-            // Check if we have at least 2 columns
-            if record.len() < 2 {
+            // Check if we have at least 2 columns, unless chapter numbers come from the URLs
+            if record.len() < 2 && !self.numbering.uses_urls() {
                 return Err(ScrapperError::csv(format!(
                     "CSV must have at least 2 columns (url, chapter_number), found {} columns",
                     record.len()
@@ -158,13 +468,15 @@ impl CsvReader {
 
             // Check if columns are not empty
             let url = record.get(0).unwrap_or("").trim();
-            let chapter = record.get(1).unwrap_or("").trim();
+            let chapter = self
+                .numbering
+                .chapter_number(url, record.get(1).unwrap_or(""));
 
             if url.is_empty() {
                 return Err(ScrapperError::csv("First column (URL) cannot be empty"));
             }
 
-            if chapter.is_empty() {
+            if chapter.is_empty() && !self.numbering.uses_urls() {
                 return Err(ScrapperError::csv(
                     "Second column (chapter_number) cannot be empty",
                 ));
@@ -180,30 +492,45 @@ impl CsvReader {
 
     /// Get basic statistics about the CSV file
     pub async fn get_stats(&self) -> ScrapperResult<CsvStats> {
-        let file = File::open(&self.file_path).await.map_err(|e| {
-            ScrapperError::file_system(
-                format!("Failed to open CSV file for stats: {e}"),
-                Some(self.file_path.clone()),
-            )
-        })?;
+        let file = self.open_input("for stats").await?;
 
-        let mut reader = AsyncReader::from_reader(file);
+        let mut reader = flexible_reader(file);
         let mut records = reader.records();
         let mut stats = CsvStats::default();
+        let mut seen_urls = self.seen_urls();
+        // The header row is line 1
+        let mut line_number = 1;
 
         while let Some(record) = records.next().await {
-            match record {
+            line_number += 1;
+            let reason = match record {
                 Ok(record) => {
+                    if let Some(position) = record.position() {
+                        line_number = position.line();
+                    }
+                    if is_blank_row(&record) {
+                        stats.blank_rows += 1;
+                        continue;
+                    }
                     stats.total_rows += 1;
-                    if record.len() >= 2 {
-                        stats.valid_rows += 1;
-                    } else {
-                        stats.invalid_rows += 1;
+                    let reason = row_warning(&record, &self.allowed_schemes, &self.numbering);
+                    if reason.is_none() && seen_urls.is_duplicate(record.get(0).unwrap_or("")) {
+                        stats.duplicate_rows += 1;
                     }
+                    reason
                 }
-                Err(_) => {
+                Err(e) => Some(CsvWarningReason::Unreadable(e.to_string())),
+            };
+
+            match reason {
+                Some(reason) => {
                     stats.invalid_rows += 1;
+                    stats.warnings.push(CsvWarning {
+                        line: line_number,
+                        reason,
+                    });
                 }
+                None => stats.valid_rows += 1,
             }
         }
 
@@ -211,11 +538,180 @@ impl CsvReader {
     }
 }
 
+/// Pick `amount` records uniformly at random, keeping their CSV order. The same seed always
+/// picks the same records from the same input.
+pub fn sample_records(records: Vec<ChapterRecord>, amount: usize, seed: u64) -> Vec<ChapterRecord> {
+    if amount >= records.len() {
+        return records;
+    }
+
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let mut picked = rand::seq::index::sample(&mut rng, records.len(), amount).into_vec();
+    picked.sort_unstable();
+
+    let mut picked = picked.into_iter().peekable();
+    records
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, record)| picked.next_if_eq(&index).map(|_| record))
+        .collect()
+}
+
+/// Keep the records whose chapter number is one of `chapters`, in CSV order. Also returns the
+/// requested chapter numbers no record has.
+pub fn select_chapters(
+    records: Vec<ChapterRecord>,
+    chapters: &[String],
+) -> (Vec<ChapterRecord>, Vec<String>) {
+    let wanted: HashSet<&str> = chapters.iter().map(|chapter| chapter.trim()).collect();
+    let selected: Vec<ChapterRecord> = records
+        .into_iter()
+        .filter(|record| wanted.contains(record.chapter_number.as_str()))
+        .collect();
+
+    let found: HashSet<&str> = selected
+        .iter()
+        .map(|record| record.chapter_number.as_str())
+        .collect();
+    let mut unknown: Vec<String> = Vec::new();
+    for chapter in chapters.iter().map(|chapter| chapter.trim()) {
+        if !found.contains(chapter) && !unknown.iter().any(|u| u == chapter) {
+            unknown.push(chapter.to_string());
+        }
+    }
+
+    (selected, unknown)
+}
+
+/// Drop the records whose chapter number appears in `excluded`, returning how many were dropped
+pub fn exclude_chapters(
+    records: Vec<ChapterRecord>,
+    excluded: &[ChapterRecord],
+) -> (Vec<ChapterRecord>, usize) {
+    let excluded: HashSet<&str> = excluded
+        .iter()
+        .map(|record| record.chapter_number.as_str())
+        .collect();
+    let total = records.len();
+    let kept: Vec<ChapterRecord> = records
+        .into_iter()
+        .filter(|record| !excluded.contains(record.chapter_number.as_str()))
+        .collect();
+    let dropped = total - kept.len();
+    (kept, dropped)
+}
+
+/// Write records as a `url,chapter_number` CSV that can be used as input for a later run
+pub async fn write_records(path: &Path, records: &[ChapterRecord]) -> ScrapperResult<()> {
+    let file = File::create(path).await.map_err(|e| {
+        ScrapperError::file_system(
+            format!("Failed to create CSV file: {e}"),
+            Some(path.to_path_buf()),
+        )
+    })?;
+
+    let mut writer = AsyncWriter::from_writer(file);
+    writer.write_record(&["url", "chapter_number"]).await?;
+    for record in records {
+        writer
+            .write_record(&[record.url.as_str(), record.chapter_number.as_str()])
+            .await?;
+    }
+    writer.flush().await?;
+
+    Ok(())
+}
+
+/// Why a row would be rejected by `read_records`, or is unusable
+#[derive(Debug, Clone, PartialEq)]
+pub enum CsvWarningReason {
+    TooFewColumns { found: usize },
+    EmptyUrl,
+    InvalidUrl(String),
+    EmptyChapter,
+    Unreadable(String),
+}
+
+impl std::fmt::Display for CsvWarningReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TooFewColumns { found } => {
+                write!(f, "expected 2 columns (url, chapter_number), found {found}")
+            }
+            Self::EmptyUrl => write!(f, "URL is empty"),
+            Self::InvalidUrl(message) => write!(f, "{message}"),
+            Self::EmptyChapter => write!(f, "chapter number is empty"),
+            Self::Unreadable(message) => write!(f, "row could not be parsed: {message}"),
+        }
+    }
+}
+
+/// A problematic row, with the line it was found on in the CSV file
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvWarning {
+    pub line: u64,
+    pub reason: CsvWarningReason,
+}
+
+impl std::fmt::Display for CsvWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.reason)
+    }
+}
+
+/// A reader accepting rows with any number of fields, so short or blank rows are reported
+/// as such rather than as parse errors
+fn flexible_reader(file: File) -> AsyncReader<File> {
+    AsyncReaderBuilder::new().flexible(true).create_reader(file)
+}
+
+/// A row with no content in any field, such as trailing `,` or whitespace-only lines.
+/// Truly empty lines are already dropped by the CSV parser.
+fn is_blank_row(record: &StringRecord) -> bool {
+    record.iter().all(|field| field.trim().is_empty())
+}
+
+/// Check a row the same way `read_records` does, returning the first problem found
+fn row_warning(
+    record: &StringRecord,
+    allowed_schemes: &[String],
+    numbering: &ChapterNumbering,
+) -> Option<CsvWarningReason> {
+    if record.len() < 2 && !numbering.uses_urls() {
+        return Some(CsvWarningReason::TooFewColumns {
+            found: record.len(),
+        });
+    }
+
+    let url = record.get(0).unwrap_or("").trim();
+    if url.is_empty() {
+        return Some(CsvWarningReason::EmptyUrl);
+    }
+    if let Err(message) = urls::validate_url(url, allowed_schemes) {
+        return Some(CsvWarningReason::InvalidUrl(message));
+    }
+
+    if numbering
+        .chapter_number(url, record.get(1).unwrap_or(""))
+        .is_empty()
+    {
+        return Some(CsvWarningReason::EmptyChapter);
+    }
+
+    None
+}
+
 #[derive(Debug, Default)]
 pub struct CsvStats {
     pub total_rows: usize,
     pub valid_rows: usize,
     pub invalid_rows: usize,
+    /// Rows with no content at all, skipped rather than counted as invalid
+    pub blank_rows: usize,
+    /// Valid rows left out because `canonicalize_urls` found their URL earlier in the file
+    pub duplicate_rows: usize,
+    /// One entry per invalid row, in file order
+    pub warnings: Vec<CsvWarning>,
 }
 
 impl CsvStats {
@@ -227,3 +723,331 @@ impl CsvStats {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TempDir;
+    use crate::test_server::{serve_once, serve_once_recording};
+    use crate::types::Auth;
+
+    fn schemes() -> Vec<String> {
+        vec!["http".to_string(), "https".to_string()]
+    }
+
+    #[test]
+    fn test_row_warning_reasons() {
+        let check = |fields: &[&str]| {
+            row_warning(
+                &StringRecord::from(fields.to_vec()),
+                &schemes(),
+                &ChapterNumbering::default(),
+            )
+        };
+
+        assert_eq!(check(&["https://example.com/1", "1"]), None);
+        assert_eq!(
+            check(&["https://example.com/1"]),
+            Some(CsvWarningReason::TooFewColumns { found: 1 })
+        );
+        assert_eq!(check(&["  ", "1"]), Some(CsvWarningReason::EmptyUrl));
+        assert!(matches!(
+            check(&["ftp://example.com/1", "1"]),
+            Some(CsvWarningReason::InvalidUrl(_))
+        ));
+        assert_eq!(
+            check(&["https://example.com/1", ""]),
+            Some(CsvWarningReason::EmptyChapter)
+        );
+    }
+
+    #[test]
+    fn test_chapter_number_from_url() {
+        let numbering = |always, strip_leading_zeros| ChapterNumbering {
+            regex: Some(Regex::new(r"/chapter-([\d.]+)/?$").unwrap()),
+            always,
+            strip_leading_zeros,
+        };
+        let url = "https://example.com/novel/chapter-0012/";
+
+        assert_eq!(numbering(false, true).chapter_number(url, ""), "12");
+        assert_eq!(numbering(false, false).chapter_number(url, " "), "0012");
+        assert_eq!(numbering(false, true).chapter_number(url, "12a"), "12a");
+        assert_eq!(numbering(true, true).chapter_number(url, "12a"), "12");
+        assert_eq!(
+            numbering(true, true).chapter_number("https://example.com/about", "3"),
+            "3"
+        );
+        assert_eq!(
+            numbering(false, true).chapter_number("https://example.com/about", ""),
+            ""
+        );
+
+        assert_eq!(strip_leading_zeros("000"), "0");
+        assert_eq!(strip_leading_zeros("0.5"), "0.5");
+        assert_eq!(strip_leading_zeros("007.5"), "7.5");
+    }
+
+    #[test]
+    fn test_sample_records_is_seeded_and_ordered() {
+        let records: Vec<ChapterRecord> = (1..=100)
+            .map(|n| ChapterRecord::new(format!("https://example.com/{n}"), n.to_string()))
+            .collect();
+        let chapters = |sample: &[ChapterRecord]| -> Vec<usize> {
+            sample
+                .iter()
+                .map(|r| r.chapter_number.parse().unwrap())
+                .collect()
+        };
+
+        let first = chapters(&sample_records(records.clone(), 10, 42));
+        let again = chapters(&sample_records(records.clone(), 10, 42));
+        let other = chapters(&sample_records(records.clone(), 10, 7));
+
+        assert_eq!(first.len(), 10);
+        assert_eq!(first, again);
+        assert_ne!(first, other);
+        assert!(first.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(sample_records(records, 500, 42).len(), 100);
+    }
+
+    #[test]
+    fn test_select_chapters_reports_unknown_numbers() {
+        let records: Vec<ChapterRecord> = ["1", "12", "server-intro", "45"]
+            .iter()
+            .map(|n| ChapterRecord::new(format!("https://example.com/{n}"), n.to_string()))
+            .collect();
+        let only: Vec<String> = ["45", " 12", "99", "server-intro", "99"]
+            .iter()
+            .map(|n| n.to_string())
+            .collect();
+
+        let (selected, unknown) = select_chapters(records, &only);
+
+        let numbers: Vec<&str> = selected.iter().map(|r| r.chapter_number.as_str()).collect();
+        assert_eq!(numbers, ["12", "server-intro", "45"]);
+        assert_eq!(unknown, ["99"]);
+    }
+
+    #[test]
+    fn test_exclude_chapters_by_number() {
+        let records: Vec<ChapterRecord> = (1..=5)
+            .map(|n| ChapterRecord::new(format!("https://example.com/{n}"), n.to_string()))
+            .collect();
+        let excluded = vec![
+            ChapterRecord::new("https://mirror.example.com/2".to_string(), "2".to_string()),
+            ChapterRecord::new("https://example.com/9".to_string(), "9".to_string()),
+        ];
+
+        let (kept, dropped) = exclude_chapters(records, &excluded);
+
+        let numbers: Vec<&str> = kept.iter().map(|r| r.chapter_number.as_str()).collect();
+        assert_eq!(numbers, ["1", "3", "4", "5"]);
+        assert_eq!(dropped, 1);
+    }
+
+    #[tokio::test]
+    async fn test_input_url_is_downloaded_once() {
+        let url = serve_once(
+            "200 OK",
+            &[("Content-Type", "text/csv; charset=utf-8")],
+            "url,chapter_number\nhttps://example.com/1,1\nhttps://example.com/2,2\n",
+        ) + "/links.csv";
+        let reader = CsvReader::new(&Config {
+            input_file: PathBuf::from(&url),
+            ..Config::default()
+        });
+
+        reader.validate_format().await.unwrap();
+        assert_eq!(reader.get_stats().await.unwrap().valid_rows, 2);
+        let records = reader.read_records().await.unwrap();
+        assert_eq!(records[1].url, "https://example.com/2");
+
+        let downloaded = reader
+            .download
+            .as_ref()
+            .unwrap()
+            .path
+            .get()
+            .unwrap()
+            .clone();
+        assert!(downloaded.exists());
+        drop(reader);
+        assert!(!downloaded.exists());
+    }
+
+    #[tokio::test]
+    async fn test_input_url_is_fetched_with_the_run_client() {
+        let (base, requests) = serve_once_recording(
+            "200 OK",
+            &[("Content-Type", "text/csv")],
+            "url,chapter_number\nhttps://example.com/1,1\n",
+        );
+        let config = Config {
+            input_file: PathBuf::from(base + "/private.csv"),
+            auth: Some(Auth::Bearer {
+                token: "secret".to_string(),
+            }),
+            ..Config::default()
+        };
+        let reader =
+            CsvReader::new(&config).with_client(web_scraper::http_client(&config).unwrap());
+
+        assert_eq!(reader.read_records().await.unwrap().len(), 1);
+        let request = requests.recv().unwrap().to_lowercase();
+        assert!(
+            request.contains("authorization: bearer secret"),
+            "{request}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_input_url_serving_html_is_rejected() {
+        let url = serve_once(
+            "200 OK",
+            &[("Content-Type", "text/html")],
+            "<html><body>Sign in</body></html>",
+        ) + "/links.csv";
+        let reader = CsvReader::new(&Config {
+            input_file: PathBuf::from(&url),
+            ..Config::default()
+        });
+
+        let error = reader.validate_format().await.unwrap_err().to_string();
+        assert!(error.contains("'text/html', not CSV"), "{error}");
+        assert!(is_url(Path::new("HTTPS://example.com/links.csv")));
+        assert!(!is_url(Path::new("./out/links.csv")));
+    }
+
+    #[tokio::test]
+    async fn test_byte_order_mark_is_ignored() {
+        let dir = TempDir::new("bom");
+        let path = dir.join("links.csv");
+        tokio::fs::write(
+            &path,
+            "\u{FEFF}url,chapter_number\nhttps://example.com/1,1\nhttps://example.com/2,2\n",
+        )
+        .await
+        .unwrap();
+
+        let config = Config {
+            input_file: path.clone(),
+            ..Config::default()
+        };
+        let reader = CsvReader::new(&config);
+        let mut start = String::new();
+        reader
+            .open_input("")
+            .await
+            .unwrap()
+            .read_to_string(&mut start)
+            .await
+            .unwrap();
+        let validated = reader.validate_format().await;
+        let records = reader.read_records().await;
+        let stats = reader.get_stats().await;
+
+        assert!(start.starts_with("url,chapter_number"));
+        validated.unwrap();
+        let records = records.unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].url, "https://example.com/1");
+        assert_eq!(records[0].chapter_number, "1");
+        assert!(stats.unwrap().warnings.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_skip_invalid_rows() {
+        let dir = TempDir::new("invalid_rows");
+        let path = dir.join("links.csv");
+        tokio::fs::write(
+            &path,
+            "url,chapter_number\nhttps://example.com/1,1\nnot a url,2\nhttps://example.com/3,\nhttps://example.com/4,4\n",
+        )
+        .await
+        .unwrap();
+
+        let mut config = Config {
+            input_file: path.clone(),
+            output_dir: dir.join("out"),
+            ..Config::default()
+        };
+        let strict = CsvReader::new(&config).read_records().await;
+        config.skip_invalid_rows = true;
+        let reader = CsvReader::new(&config);
+        let records = reader.read_records().await;
+        let counted = reader
+            .count_records_and_existing(&FileManager::new(&config))
+            .await;
+
+        assert!(strict.is_err());
+        let chapters: Vec<String> = records
+            .unwrap()
+            .into_iter()
+            .map(|record| record.chapter_number)
+            .collect();
+        assert_eq!(chapters, ["1", "4"]);
+        assert_eq!(counted.unwrap().total, 2);
+    }
+
+    #[tokio::test]
+    async fn test_count_notes_saved_chapter_sizes() {
+        let dir = TempDir::new("count");
+        let path = dir.join("links.csv");
+        tokio::fs::write(
+            &path,
+            "url,chapter_number\nhttps://example.com/1,1\nhttps://example.com/2,2\nhttps://example.com/3,3\n",
+        )
+        .await
+        .unwrap();
+        tokio::fs::write(dir.join("chapter_1.txt"), "a".repeat(120))
+            .await
+            .unwrap();
+        tokio::fs::write(dir.join("chapter_2.txt"), "")
+            .await
+            .unwrap();
+
+        let config = Config {
+            input_file: path,
+            output_dir: dir.path().to_path_buf(),
+            ..Config::default()
+        };
+        let counted = CsvReader::new(&config)
+            .count_records_and_existing(&FileManager::new(&config))
+            .await;
+
+        let counted = counted.unwrap();
+        assert_eq!((counted.total, counted.existing), (3, 1));
+        assert_eq!((counted.saved_files, counted.saved_bytes), (1, 120));
+    }
+
+    #[tokio::test]
+    async fn test_trailing_blank_rows_are_skipped() {
+        let dir = TempDir::new("blank_rows");
+        let path = dir.join("links.csv");
+        tokio::fs::write(
+            &path,
+            "url,chapter_number\nhttps://example.com/1,1\nhttps://example.com/2,2\n\n,\n  \n \t, \n\n",
+        )
+        .await
+        .unwrap();
+
+        let config = Config {
+            input_file: path.clone(),
+            ..Config::default()
+        };
+        let reader = CsvReader::new(&config);
+        let records = reader.read_records().await;
+        let stats = reader.get_stats().await;
+
+        let records = records.unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].chapter_number, "2");
+
+        let stats = stats.unwrap();
+        assert_eq!(stats.total_rows, 2);
+        assert_eq!(stats.valid_rows, 2);
+        assert_eq!(stats.blank_rows, 3);
+        assert!(stats.warnings.is_empty());
+    }
+}
@@ -0,0 +1,238 @@
+//! Table-of-contents crawling for `--discover`: follows a paginated chapter list and
+//! collects its chapter links into a CSV that can be fed back in as input.
+
+use crate::config::DiscoverOptions;
+use crate::csv_reader;
+use crate::error::{ScrapperError, ScrapperResult};
+use crate::types::{ChapterRecord, Config};
+use crate::urls;
+use crate::web_scraper::WebScraper;
+use scraper::{Html, Selector};
+use std::collections::HashSet;
+use std::time::Duration;
+use tokio::time::sleep;
+use url::Url;
+
+/// Safety cap on the number of table-of-contents pages followed
+const MAX_TOC_PAGES: usize = 1000;
+
+/// Links found on one table-of-contents page
+#[derive(Debug, Default)]
+struct TocPage {
+    chapter_links: Vec<Url>,
+    next_page: Option<Url>,
+}
+
+pub struct TocCrawler {
+    scraper: WebScraper,
+    link_selector: Selector,
+    next_selector: Selector,
+    allowed_schemes: Vec<String>,
+    page_delay: Duration,
+}
+
+impl TocCrawler {
+    pub fn new(config: &Config, options: &DiscoverOptions) -> ScrapperResult<Self> {
+        Ok(Self {
+            scraper: WebScraper::new(config)?,
+            link_selector: parse_selector("link_selector", &options.link_selector)?,
+            next_selector: parse_selector("next_selector", &options.next_selector)?,
+            allowed_schemes: config.allowed_schemes.clone(),
+            page_delay: Duration::from_millis(config.task_delay_ms),
+        })
+    }
+
+    /// Crawl the table of contents starting at `toc_url`, following "next page" links,
+    /// and number the chapter links in the order they were found
+    pub async fn discover(&self, toc_url: &str) -> ScrapperResult<Vec<ChapterRecord>> {
+        let mut page_url = urls::validate_url(toc_url, &self.allowed_schemes)
+            .map_err(|message| ScrapperError::validation("discover", message))?;
+
+        let mut visited_pages = HashSet::new();
+        let mut seen_links = HashSet::new();
+        let mut records = Vec::new();
+
+        while visited_pages.len() < MAX_TOC_PAGES && visited_pages.insert(page_url.clone()) {
+            let html = self.scraper.fetch_page(page_url.as_str()).await?;
+            let page = self.parse_page(&html, &page_url);
+
+            println!(
+                "📑 {} chapter links on {page_url}",
+                page.chapter_links.len()
+            );
+
+            for link in page.chapter_links {
+                if seen_links.insert(link.clone()) {
+                    records.push(ChapterRecord::new(
+                        link.to_string(),
+                        (records.len() + 1).to_string(),
+                    ));
+                }
+            }
+
+            match page.next_page {
+                Some(next_page) => page_url = next_page,
+                None => break,
+            }
+
+            sleep(self.page_delay).await;
+        }
+
+        if records.is_empty() {
+            return Err(ScrapperError::content_extraction(
+                toc_url,
+                "No chapter links found. Check the --link-selector",
+            ));
+        }
+
+        Ok(records)
+    }
+
+    /// Collect chapter links and the next-page link from a table-of-contents page
+    fn parse_page(&self, html: &str, page_url: &Url) -> TocPage {
+        let document = Html::parse_document(html);
+
+        let next_page = document
+            .select(&self.next_selector)
+            .filter_map(|element| element.value().attr("href"))
+            .find_map(|href| self.resolve_link(page_url, href));
+
+        let chapter_links = document
+            .select(&self.link_selector)
+            .filter_map(|element| element.value().attr("href"))
+            .filter_map(|href| self.resolve_link(page_url, href))
+            // The pagination link is not a chapter, even when the link selector matches it
+            .filter(|link| Some(link) != next_page.as_ref() && link != page_url)
+            .collect();
+
+        TocPage {
+            chapter_links,
+            next_page,
+        }
+    }
+
+    /// Resolve an href against its page, dropping fragments and links with disallowed schemes
+    fn resolve_link(&self, page_url: &Url, href: &str) -> Option<Url> {
+        let mut link = page_url.join(href.trim()).ok()?;
+        link.set_fragment(None);
+
+        urls::validate_url(link.as_str(), &self.allowed_schemes)
+            .is_ok()
+            .then_some(link)
+    }
+}
+
+fn parse_selector(field: &str, selector: &str) -> ScrapperResult<Selector> {
+    Selector::parse(selector).map_err(|e| {
+        ScrapperError::validation(field, format!("Invalid CSS selector '{selector}': {e:?}"))
+    })
+}
+
+/// Run `--discover`: crawl the table of contents and write the links CSV
+pub async fn run(config: &Config, options: &DiscoverOptions) -> ScrapperResult<()> {
+    if options.output.is_none() && csv_reader::is_url(&config.input_file) {
+        return Err(ScrapperError::config(
+            "input_file is a URL, so discovered links can't be written there; use --discover-output",
+        ));
+    }
+    // Only overwrite an existing links CSV when --discover-output names it explicitly
+    if options.output.is_none() && config.input_file.exists() {
+        return Err(ScrapperError::config(format!(
+            "{:?} already exists; pass --discover-output {:?} to overwrite it, or another path",
+            config.input_file, config.input_file
+        )));
+    }
+
+    let crawler = TocCrawler::new(config, options)?;
+    let records = crawler.discover(&options.toc_url).await?;
+
+    let output = options.output.as_ref().unwrap_or(&config.input_file);
+    if let Some(parent) = output.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        tokio::fs::create_dir_all(parent).await.map_err(|e| {
+            ScrapperError::file_system(
+                format!("Failed to create directory for discovered links: {e}"),
+                Some(parent.to_path_buf()),
+            )
+        })?;
+    }
+
+    csv_reader::write_records(output, &records).await?;
+    println!(
+        "✅ Discovered {} chapter links, written to {output:?}",
+        records.len()
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn crawler() -> TocCrawler {
+        let options = DiscoverOptions {
+            toc_url: "https://example.com/toc".to_string(),
+            link_selector: ".chapters a".to_string(),
+            next_selector: "a.next".to_string(),
+            output: None,
+        };
+        TocCrawler::new(&Config::default(), &options).unwrap()
+    }
+
+    #[test]
+    fn test_parse_page_resolves_links_and_pagination() {
+        let html = r#"<ul class="chapters">
+            <li><a href="/chapter/1">One</a></li>
+            <li><a href="chapter/2#top">Two</a></li>
+            <li><a href="javascript:void(0)">Bad</a></li>
+            <li><a class="next" href="?page=2">Next</a></li>
+        </ul>"#;
+        let page_url = Url::parse("https://example.com/novel/toc").unwrap();
+
+        let page = crawler().parse_page(html, &page_url);
+
+        let links: Vec<&str> = page.chapter_links.iter().map(Url::as_str).collect();
+        assert_eq!(
+            links,
+            vec![
+                "https://example.com/chapter/1",
+                "https://example.com/novel/chapter/2"
+            ]
+        );
+        assert_eq!(
+            page.next_page.as_ref().map(Url::as_str),
+            Some("https://example.com/novel/toc?page=2")
+        );
+    }
+
+    #[test]
+    fn test_parse_page_without_pagination() {
+        let html = r#"<div class="chapters"><a href="/c1">One</a></div>"#;
+        let page_url = Url::parse("https://example.com/toc").unwrap();
+
+        let page = crawler().parse_page(html, &page_url);
+
+        assert_eq!(page.chapter_links.len(), 1);
+        assert!(page.next_page.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_existing_input_file_is_not_overwritten_by_default() {
+        let config = Config {
+            input_file: "Cargo.toml".into(),
+            ..Config::default()
+        };
+        let options = DiscoverOptions {
+            toc_url: "http://127.0.0.1:1/toc".to_string(),
+            link_selector: "a".to_string(),
+            next_selector: "a.next".to_string(),
+            output: None,
+        };
+
+        let err = run(&config, &options).await.unwrap_err();
+
+        assert!(err.to_string().contains("--discover-output"), "{err}");
+    }
+}
@@ -43,6 +43,70 @@ pub enum ScrapperError {
         message: String,
         path: Option<PathBuf>,
     },
+
+    #[error("Browser rendering error for URL '{url}': {message}")]
+    Render { url: String, message: String },
+
+    #[error("Timed out after {timeout_ms}ms waiting for selector '{selector}' on URL '{url}'")]
+    SelectorTimeout {
+        url: String,
+        selector: String,
+        timeout_ms: u64,
+    },
+
+    #[error("Run aborted: {reason}")]
+    Aborted { reason: String },
+}
+
+/// The variant of a `ScrapperError`, without its details, for counting failures by cause
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ErrorKind {
+    Config,
+    Csv,
+    FileSystem,
+    WebScraping,
+    ContentExtraction,
+    Http,
+    TaskExecution,
+    Progress,
+    Validation,
+    Io,
+    Render,
+    SelectorTimeout,
+    Aborted,
+}
+
+impl ErrorKind {
+    /// Failures getting the page at all, usually fixed with delays, lower concurrency or a proxy
+    pub fn is_network(self) -> bool {
+        matches!(self, Self::Http | Self::WebScraping | Self::Render)
+    }
+
+    /// Failures finding content in a page that loaded, usually fixed with the selector settings
+    pub fn is_extraction(self) -> bool {
+        matches!(self, Self::ContentExtraction | Self::SelectorTimeout)
+    }
+}
+
+impl std::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let label = match self {
+            Self::Config => "Configuration",
+            Self::Csv => "CSV",
+            Self::FileSystem => "File system",
+            Self::WebScraping => "Web scraping",
+            Self::ContentExtraction => "Content extraction",
+            Self::Http => "HTTP/network",
+            Self::TaskExecution => "Task execution",
+            Self::Progress => "Progress tracking",
+            Self::Validation => "Validation",
+            Self::Io => "I/O",
+            Self::Render => "Browser rendering",
+            Self::SelectorTimeout => "Selector timeout",
+            Self::Aborted => "Aborted",
+        };
+        f.write_str(label)
+    }
 }
 
 impl ScrapperError {
@@ -123,6 +187,53 @@ impl ScrapperError {
         }
     }
 
+    /// Create an error for a page the headless browser failed to render
+    pub fn render<U: Into<String>, S: Into<String>>(url: U, message: S) -> Self {
+        Self::Render {
+            url: url.into(),
+            message: message.into(),
+        }
+    }
+
+    /// Create an error for a rendered page where `wait_for_selector` never appeared
+    pub fn selector_timeout<U: Into<String>, S: Into<String>>(
+        url: U,
+        selector: S,
+        timeout_ms: u64,
+    ) -> Self {
+        Self::SelectorTimeout {
+            url: url.into(),
+            selector: selector.into(),
+            timeout_ms,
+        }
+    }
+
+    /// Create an error for a run stopped early on purpose
+    pub fn aborted<S: Into<String>>(reason: S) -> Self {
+        Self::Aborted {
+            reason: reason.into(),
+        }
+    }
+
+    /// The variant of this error, for counting failures by cause
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            ScrapperError::Config { .. } => ErrorKind::Config,
+            ScrapperError::Csv { .. } => ErrorKind::Csv,
+            ScrapperError::FileSystem { .. } => ErrorKind::FileSystem,
+            ScrapperError::WebScraping { .. } => ErrorKind::WebScraping,
+            ScrapperError::ContentExtraction { .. } => ErrorKind::ContentExtraction,
+            ScrapperError::Http { .. } => ErrorKind::Http,
+            ScrapperError::TaskExecution { .. } => ErrorKind::TaskExecution,
+            ScrapperError::Progress { .. } => ErrorKind::Progress,
+            ScrapperError::Validation { .. } => ErrorKind::Validation,
+            ScrapperError::Io { .. } => ErrorKind::Io,
+            ScrapperError::Render { .. } => ErrorKind::Render,
+            ScrapperError::SelectorTimeout { .. } => ErrorKind::SelectorTimeout,
+            ScrapperError::Aborted { .. } => ErrorKind::Aborted,
+        }
+    }
+
     /// Check if the error is recoverable (temporary network issues, etc.)
     pub fn is_recoverable(&self) -> bool {
         matches!(
@@ -139,7 +250,9 @@ impl ScrapperError {
         match self {
             ScrapperError::WebScraping { url, .. }
             | ScrapperError::ContentExtraction { url, .. }
-            | ScrapperError::Http { url, .. } => Some(url),
+            | ScrapperError::Http { url, .. }
+            | ScrapperError::Render { url, .. }
+            | ScrapperError::SelectorTimeout { url, .. } => Some(url),
             _ => None,
         }
     }
@@ -216,6 +329,25 @@ impl ScrapperError {
                     format!("I/O error: {message}. Check file permissions.")
                 }
             }
+            ScrapperError::Render { url, message } => {
+                format!(
+                    "Couldn't render {url} in the headless browser: {message}. Check that Chrome or Chromium is installed."
+                )
+            }
+            ScrapperError::SelectorTimeout {
+                url,
+                selector,
+                timeout_ms,
+            } => {
+                format!(
+                    "'{selector}' never appeared on {url} within {timeout_ms}ms. Check wait_for_selector or raise wait_timeout_ms."
+                )
+            }
+            ScrapperError::Aborted { reason } => {
+                format!(
+                    "Run aborted: {reason}. The site may be blocking requests; unprocessed chapters were saved for a later run."
+                )
+            }
         }
     }
 
@@ -233,7 +365,8 @@ impl ScrapperError {
                 format!("Path: {path:?}, Details: {message}")
             }
             ScrapperError::WebScraping { url, message }
-            | ScrapperError::ContentExtraction { url, message } => {
+            | ScrapperError::ContentExtraction { url, message }
+            | ScrapperError::Render { url, message } => {
                 format!("URL: {url}, Details: {message}")
             }
             ScrapperError::Validation { field, message } => {
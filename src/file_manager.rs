@@ -22,6 +22,57 @@ impl FileManagerStats {
     }
 }
 
+/// A group of chapter files sharing (nearly) the same size
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SizeCluster {
+    /// Median size within the group (bytes), reported as the size the files are "about"
+    pub size: u64,
+    /// Files in the group
+    pub count: usize,
+    /// Files considered
+    pub total: usize,
+}
+
+/// Minimum number of files before uniform sizes are considered suspicious
+const UNIFORM_SIZE_MIN_FILES: usize = 5;
+
+/// Share of files that must fall in one size group to be suspicious
+const UNIFORM_SIZE_FRACTION: f64 = 0.5;
+
+/// Find the largest group of near-identical sizes (within 1% of each other, or 4 bytes for small files)
+/// if it covers a suspiciously large share of the files
+pub fn uniform_size_cluster(sizes: &[u64]) -> Option<SizeCluster> {
+    if sizes.len() < UNIFORM_SIZE_MIN_FILES {
+        return None;
+    }
+
+    let mut sorted = sizes.to_vec();
+    sorted.sort_unstable();
+
+    // Sliding window over sorted sizes: widest run whose spread stays within tolerance
+    let mut best = (0, 0);
+    let mut start = 0;
+    for end in 0..sorted.len() {
+        while sorted[end] - sorted[start] > (sorted[start] / 100).max(4) {
+            start += 1;
+        }
+        if end - start > best.1 - best.0 {
+            best = (start, end);
+        }
+    }
+
+    let count = best.1 - best.0 + 1;
+    if (count as f64) < sizes.len() as f64 * UNIFORM_SIZE_FRACTION {
+        return None;
+    }
+
+    Some(SizeCluster {
+        size: sorted[best.0 + count / 2],
+        count,
+        total: sizes.len(),
+    })
+}
+
 #[derive(Debug, Default)]
 pub struct CleanupStats {
     pub total_checked: usize,
@@ -34,28 +85,278 @@ impl CleanupStats {
         self.removed_empty + self.removed_small
     }
 }
-use crate::types::ChapterRecord;
+#[cfg(feature = "sqlite")]
+use crate::sqlite_sink::SqliteSink;
+use crate::types::{ChapterRecord, Config, ExistingPolicy, OutputLayout};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
 use tokio::fs;
 
+/// CSV of records left unprocessed when a run stops early, ready to be used as input later
+const REMAINING_RECORDS_FILE: &str = "remaining_links.csv";
+
+/// Extension of chapter content files unless `output_extension` is set
+pub const DEFAULT_CHAPTER_EXTENSION: &str = "txt";
+
+/// Stem of the content file inside a chapter bundle folder
+const BUNDLE_INDEX_STEM: &str = "index";
+
+/// Name of the response headers sidecar inside a chapter bundle folder
+const BUNDLE_HEADERS_FILE: &str = "headers.txt";
+
+/// Extension of the response headers sidecar written next to a flat chapter file
+const HEADERS_EXTENSION: &str = "headers.txt";
+
+/// CSV of chapters found missing or broken by `--verify`
+const VERIFY_FAILURES_FILE: &str = "verify_failures.csv";
+
+/// Prefix of the volume files written by `merge_volumes`
+const VOLUME_FILE_PREFIX: &str = "volume_";
+
+/// Appended to a chapter's path for the previous version kept by refresh mode
+const BACKUP_SUFFIX: &str = ".bak";
+
+/// Name of the folder holding downloaded images inside a chapter bundle
+pub const BUNDLE_ASSETS_DIR: &str = "assets";
+
+/// Hex digits of the hash appended to truncated file names
+const NAME_HASH_LEN: usize = 8;
+
+/// Fit `stem.extension` (or just `stem` without an extension) within `max_len` bytes.
+/// Over-long stems are cut at a char boundary and get a hash of the full stem appended,
+/// so different long names never collide.
+pub fn bounded_file_name(stem: &str, extension: Option<&str>, max_len: usize) -> String {
+    let suffix = extension.map(|ext| format!(".{ext}")).unwrap_or_default();
+    if stem.len() + suffix.len() <= max_len {
+        return format!("{stem}{suffix}");
+    }
+
+    let digest = Sha256::digest(stem.as_bytes());
+    let hash: String = digest
+        .iter()
+        .take(NAME_HASH_LEN / 2)
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+
+    let mut keep = max_len.saturating_sub(suffix.len() + 1 + NAME_HASH_LEN);
+    while !stem.is_char_boundary(keep) {
+        keep -= 1;
+    }
+
+    format!("{}_{hash}{suffix}", &stem[..keep])
+}
+
+/// Result of merging chapters into volume files
+#[derive(Debug, Default)]
+pub struct MergeStats {
+    pub volumes: Vec<PathBuf>,
+    pub chapters: usize,
+    /// Chapters marked as missing in their volume, in order
+    pub missing: Vec<String>,
+}
+
+/// Concatenate chapters into the text of one volume, with a separator before each chapter
+/// and a placeholder for chapters that have no content
+fn volume_text(chapters: &[(&str, Option<String>)]) -> String {
+    let mut text = String::new();
+
+    for (chapter_number, content) in chapters {
+        text.push_str(&format!("===== Chapter {chapter_number} =====\n\n"));
+        match content {
+            Some(content) => text.push_str(content.trim()),
+            None => text.push_str(&format!(
+                "[Chapter {chapter_number} is missing: it was not scraped successfully]"
+            )),
+        }
+        text.push_str("\n\n");
+    }
+
+    text
+}
+
 pub struct FileManager {
     output_dir: PathBuf,
+    output_layout: OutputLayout,
+    existing_policy: ExistingPolicy,
+    // Chapters picked with `only_chapters` are scraped again even when they exist
+    overwrite_existing: bool,
+    chapter_extension: String,
+    max_filename_len: usize,
+    write_probe_prefix: String,
+    skip_write_check: bool,
+    max_output_files: Option<usize>,
+    max_output_bytes: Option<u64>,
+    // Output written during this run, checked against the limits above
+    written_files: AtomicUsize,
+    written_bytes: AtomicU64,
+    /// Where chapters are stored instead of the output directory, with `sqlite_database`
+    #[cfg(feature = "sqlite")]
+    database: Option<SqliteSink>,
 }
 
 impl FileManager {
-    pub fn new<P: AsRef<Path>>(output_dir: P) -> Self {
+    pub fn new(config: &Config) -> Self {
         Self {
-            output_dir: output_dir.as_ref().to_path_buf(),
+            output_dir: config.output_dir.clone(),
+            output_layout: config.output_layout,
+            existing_policy: config.existing_policy,
+            overwrite_existing: !config.only_chapters.is_empty(),
+            chapter_extension: config
+                .output_extension
+                .as_deref()
+                .unwrap_or(DEFAULT_CHAPTER_EXTENSION)
+                .trim_start_matches('.')
+                .to_string(),
+            max_filename_len: config.max_filename_len,
+            write_probe_prefix: config.write_probe_prefix.clone(),
+            skip_write_check: config.skip_write_check,
+            max_output_files: config.max_output_files,
+            max_output_bytes: config.max_output_bytes,
+            written_files: AtomicUsize::new(0),
+            written_bytes: AtomicU64::new(0),
+            #[cfg(feature = "sqlite")]
+            database: None,
         }
     }
 
+    /// Look chapters up in a database instead of the output directory
+    #[cfg(feature = "sqlite")]
+    pub fn with_database(mut self, database: SqliteSink) -> Self {
+        self.database = Some(database);
+        self
+    }
+
+    /// Account for a chapter written during this run
+    pub fn record_write(&self, bytes: u64) {
+        self.written_files.fetch_add(1, Ordering::Relaxed);
+        self.written_bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    /// Describe the output limit that has been reached, if any
+    pub fn output_limit_reached(&self) -> Option<String> {
+        let files = self.written_files.load(Ordering::Relaxed);
+        let bytes = self.written_bytes.load(Ordering::Relaxed);
+
+        if let Some(max_files) = self.max_output_files
+            && files >= max_files
+        {
+            return Some(format!("wrote {files} chapter files (limit {max_files})"));
+        }
+
+        if let Some(max_bytes) = self.max_output_bytes
+            && bytes >= max_bytes
+        {
+            return Some(format!("wrote {bytes} bytes (limit {max_bytes})"));
+        }
+
+        None
+    }
+
+    /// Where records left unprocessed by an early stop are written
+    pub fn remaining_records_path(&self) -> PathBuf {
+        self.output_dir.join(REMAINING_RECORDS_FILE)
+    }
+
+    /// Where `--verify` writes the chapters that need to be scraped again
+    pub fn verify_failures_path(&self) -> PathBuf {
+        self.output_dir.join(VERIFY_FAILURES_FILE)
+    }
+
+    /// Whether a chapter should be left alone because it already exists
+    pub fn should_skip(&self, record: &ChapterRecord) -> bool {
+        self.skips_existing() && self.chapter_exists(record)
+    }
+
+    /// `should_skip` for a record whose file size `saved_chapter_size` already looked up, so
+    /// callers needing both only read the file's metadata once
+    pub fn should_skip_saved(&self, record: &ChapterRecord, saved_size: Option<u64>) -> bool {
+        self.skips_existing()
+            && self
+                .stored_in_database(record)
+                .unwrap_or(saved_size.is_some())
+    }
+
+    /// Whether existing chapters are left alone rather than fetched again
+    fn skips_existing(&self) -> bool {
+        self.existing_policy == ExistingPolicy::Skip && !self.overwrite_existing
+    }
+
     pub fn chapter_exists(&self, record: &ChapterRecord) -> bool {
-        let path = self.get_chapter_path(record);
-        path.exists() && self.is_file_valid(&path)
+        self.stored_in_database(record)
+            .unwrap_or_else(|| self.saved_chapter_size(record).is_some())
+    }
+
+    /// Whether the chapter is in the database, when chapters go to one instead of files
+    #[cfg(feature = "sqlite")]
+    fn stored_in_database(&self, record: &ChapterRecord) -> Option<bool> {
+        self.database
+            .as_ref()
+            .map(|database| database.contains(&record.chapter_number))
+    }
+
+    #[cfg(not(feature = "sqlite"))]
+    fn stored_in_database(&self, _record: &ChapterRecord) -> Option<bool> {
+        None
+    }
+
+    /// Size of a chapter's saved text file, if it has a non-empty one
+    pub fn saved_chapter_size(&self, record: &ChapterRecord) -> Option<u64> {
+        let metadata = std::fs::metadata(self.get_chapter_path(record)).ok()?;
+        (metadata.is_file() && metadata.len() > 0).then_some(metadata.len())
     }
 
+    /// Path of the file holding a chapter's text, according to the output layout
     pub fn get_chapter_path(&self, record: &ChapterRecord) -> PathBuf {
-        self.output_dir.join(record.file_name())
+        match self.output_layout {
+            OutputLayout::Flat => self.output_dir.join(self.chapter_file_name(record)),
+            OutputLayout::Bundle => self
+                .output_dir
+                .join(bounded_file_name(
+                    &record.base_name(),
+                    None,
+                    self.max_filename_len,
+                ))
+                .join(self.bundle_index_file()),
+        }
+    }
+
+    /// File name of a chapter in the flat layout, kept within `max_filename_len`
+    pub fn chapter_file_name(&self, record: &ChapterRecord) -> String {
+        bounded_file_name(
+            &record.base_name(),
+            Some(&self.chapter_extension),
+            self.max_filename_len,
+        )
+    }
+
+    fn bundle_index_file(&self) -> String {
+        format!("{BUNDLE_INDEX_STEM}.{}", self.chapter_extension)
+    }
+
+    /// Directory for a chapter's downloaded images (only used by the bundle layout)
+    pub fn assets_dir_for(chapter_path: &Path) -> PathBuf {
+        chapter_path
+            .parent()
+            .map(|dir| dir.join(BUNDLE_ASSETS_DIR))
+            .unwrap_or_else(|| PathBuf::from(BUNDLE_ASSETS_DIR))
+    }
+
+    /// Path of the response headers sidecar for a chapter, according to the output layout
+    pub fn headers_path_for(layout: OutputLayout, chapter_path: &Path) -> PathBuf {
+        match layout {
+            OutputLayout::Flat => chapter_path.with_extension(HEADERS_EXTENSION),
+            OutputLayout::Bundle => chapter_path.with_file_name(BUNDLE_HEADERS_FILE),
+        }
+    }
+
+    /// Where refresh mode keeps the previous version of a changed chapter
+    pub fn backup_path_for(chapter_path: &Path) -> PathBuf {
+        let mut path = chapter_path.as_os_str().to_os_string();
+        path.push(BACKUP_SUFFIX);
+        PathBuf::from(path)
     }
 
     pub async fn ensure_output_dir_exists(&self) -> ScrapperResult<()> {
@@ -70,25 +371,12 @@ impl FileManager {
         Ok(())
     }
 
-    pub fn output_dir(&self) -> &Path {
-        &self.output_dir
-    }
-
-    /// Check if a file exists and has content (not empty)
-    fn is_file_valid(&self, path: &Path) -> bool {
-        if let Ok(metadata) = std::fs::metadata(path) {
-            metadata.is_file() && metadata.len() > 0
-        } else {
-            false
-        }
-    }
-
-    /// Get information about existing files in the output directory
-    pub async fn get_existing_files_info(&self) -> ScrapperResult<FileManagerStats> {
-        let mut stats = FileManagerStats::default();
+    /// Collect the content files of all chapters present in the output directory
+    async fn chapter_content_files(&self) -> ScrapperResult<Vec<PathBuf>> {
+        let mut files = Vec::new();
 
         if !self.output_dir.exists() {
-            return Ok(stats);
+            return Ok(files);
         }
 
         let mut entries = fs::read_dir(&self.output_dir).await.map_err(|e| {
@@ -105,27 +393,137 @@ impl FileManager {
             )
         })? {
             let path = entry.path();
-            if let Some(file_name) = path.file_name().and_then(|n| n.to_str()) {
-                if file_name.starts_with("chapter_") && file_name.ends_with(".txt") {
-                    let metadata = entry.metadata().await.map_err(|e| {
-                        ScrapperError::file_system(
-                            format!("Failed to read file metadata: {e}"),
-                            Some(path.clone()),
-                        )
-                    })?;
-
-                    stats.total_files += 1;
-                    stats.total_size += metadata.len();
+            let Some(file_name) = path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            if !file_name.starts_with("chapter_") {
+                continue;
+            }
 
-                    if metadata.len() == 0 {
-                        stats.empty_files += 1;
+            match self.output_layout {
+                OutputLayout::Flat
+                    if file_name.ends_with(&format!(".{}", self.chapter_extension))
+                        && !file_name.ends_with(&format!(".{HEADERS_EXTENSION}")) =>
+                {
+                    files.push(path)
+                }
+                OutputLayout::Bundle if path.is_dir() => {
+                    let index = path.join(self.bundle_index_file());
+                    if index.exists() {
+                        files.push(index);
                     }
+                }
+                _ => {}
+            }
+        }
 
-                    if metadata.len() < 100 {
-                        stats.small_files += 1;
-                    }
+        Ok(files)
+    }
+
+    /// Chapter files in the output directory that none of `records` would write, such as
+    /// unrelated files following the same naming convention
+    pub async fn untracked_chapter_files(
+        &self,
+        records: &[ChapterRecord],
+    ) -> ScrapperResult<Vec<PathBuf>> {
+        let tracked: HashSet<PathBuf> = records
+            .iter()
+            .map(|record| self.get_chapter_path(record))
+            .collect();
+
+        Ok(self
+            .chapter_content_files()
+            .await?
+            .into_iter()
+            .filter(|path| !tracked.contains(path))
+            .collect())
+    }
+
+    /// Get information about existing files in the output directory
+    pub async fn get_existing_files_info(&self) -> ScrapperResult<FileManagerStats> {
+        let mut stats = FileManagerStats::default();
+
+        for path in self.chapter_content_files().await? {
+            let metadata = fs::metadata(&path).await.map_err(|e| {
+                ScrapperError::file_system(
+                    format!("Failed to read file metadata: {e}"),
+                    Some(path.clone()),
+                )
+            })?;
+
+            stats.total_files += 1;
+            stats.total_size += metadata.len();
+
+            if metadata.len() == 0 {
+                stats.empty_files += 1;
+            }
+
+            if metadata.len() < 100 {
+                stats.small_files += 1;
+            }
+        }
+
+        Ok(stats)
+    }
+
+    /// Check whether many non-empty chapter files share a near-identical size, which usually
+    /// means a boilerplate block (like a "login required" notice) was extracted instead of content
+    pub async fn uniform_size_cluster(&self) -> ScrapperResult<Option<SizeCluster>> {
+        let mut sizes = Vec::new();
+        for path in self.chapter_content_files().await? {
+            let metadata = fs::metadata(&path).await.map_err(|e| {
+                ScrapperError::file_system(
+                    format!("Failed to read file metadata: {e}"),
+                    Some(path.clone()),
+                )
+            })?;
+            if metadata.len() > 0 {
+                sizes.push(metadata.len());
+            }
+        }
+
+        Ok(uniform_size_cluster(&sizes))
+    }
+
+    /// Concatenate chapters, in the order given, into `volume_1.txt`, `volume_2.txt`, ... of
+    /// `chapters_per_volume` chapters each. Chapters without a valid file are marked as missing.
+    pub async fn merge_volumes(
+        &self,
+        records: &[ChapterRecord],
+        chapters_per_volume: usize,
+    ) -> ScrapperResult<MergeStats> {
+        let mut stats = MergeStats::default();
+
+        for (index, volume) in records.chunks(chapters_per_volume).enumerate() {
+            let mut chapters = Vec::with_capacity(volume.len());
+            for record in volume {
+                let content = if self.chapter_exists(record) {
+                    fs::read_to_string(self.get_chapter_path(record)).await.ok()
+                } else {
+                    None
+                };
+                if content.is_none() {
+                    stats.missing.push(record.chapter_number.clone());
                 }
+                chapters.push((record.chapter_number.as_str(), content));
             }
+
+            let path = self.output_dir.join(format!(
+                "{VOLUME_FILE_PREFIX}{}.{}",
+                index + 1,
+                self.chapter_extension
+            ));
+            fs::write(&path, volume_text(&chapters))
+                .await
+                .map_err(|e| {
+                    ScrapperError::file_system(
+                        format!("Failed to write volume file: {e}"),
+                        Some(path.clone()),
+                    )
+                })?;
+
+            stats.chapters += volume.len();
+            stats.volumes.push(path);
         }
 
         Ok(stats)
@@ -135,60 +533,39 @@ impl FileManager {
     pub async fn cleanup_invalid_files(&self) -> ScrapperResult<CleanupStats> {
         let mut stats = CleanupStats::default();
 
-        if !self.output_dir.exists() {
-            return Ok(stats);
-        }
+        for path in self.chapter_content_files().await? {
+            let metadata = fs::metadata(&path).await.map_err(|e| {
+                ScrapperError::file_system(
+                    format!("Failed to read file metadata during cleanup: {e}"),
+                    Some(path.clone()),
+                )
+            })?;
 
-        let mut entries = fs::read_dir(&self.output_dir).await.map_err(|e| {
-            ScrapperError::file_system(
-                format!("Failed to read output directory for cleanup: {e}"),
-                Some(self.output_dir.clone()),
-            )
-        })?;
+            stats.total_checked += 1;
 
-        while let Some(entry) = entries.next_entry().await.map_err(|e| {
-            ScrapperError::file_system(
-                format!("Failed to read directory entry during cleanup: {e}"),
-                Some(self.output_dir.clone()),
-            )
-        })? {
-            let path = entry.path();
-            if let Some(file_name) = path.file_name().and_then(|n| n.to_str()) {
-                if file_name.starts_with("chapter_") && file_name.ends_with(".txt") {
-                    let metadata = entry.metadata().await.map_err(|e| {
+            // Remove empty files
+            if metadata.len() == 0 {
+                fs::remove_file(&path).await.map_err(|e| {
+                    ScrapperError::file_system(
+                        format!("Failed to remove empty file: {e}"),
+                        Some(path.clone()),
+                    )
+                })?;
+                stats.removed_empty += 1;
+            }
+            // Optionally remove very small files (likely failed scrapes)
+            else if metadata.len() < 50 {
+                // Check if content looks like an error message
+                if let Ok(content) = fs::read_to_string(&path).await
+                    && (content.trim().is_empty() || content.len() < 50)
+                {
+                    fs::remove_file(&path).await.map_err(|e| {
                         ScrapperError::file_system(
-                            format!("Failed to read file metadata during cleanup: {e}"),
+                            format!("Failed to remove small invalid file: {e}"),
                             Some(path.clone()),
                         )
                     })?;
-
-                    stats.total_checked += 1;
-
-                    // Remove empty files
-                    if metadata.len() == 0 {
-                        fs::remove_file(&path).await.map_err(|e| {
-                            ScrapperError::file_system(
-                                format!("Failed to remove empty file: {e}"),
-                                Some(path.clone()),
-                            )
-                        })?;
-                        stats.removed_empty += 1;
-                    }
-                    // Optionally remove very small files (likely failed scrapes)
-                    else if metadata.len() < 50 {
-                        // Check if content looks like an error message
-                        if let Ok(content) = fs::read_to_string(&path).await {
-                            if content.trim().is_empty() || content.len() < 50 {
-                                fs::remove_file(&path).await.map_err(|e| {
-                                    ScrapperError::file_system(
-                                        format!("Failed to remove small invalid file: {e}"),
-                                        Some(path.clone()),
-                                    )
-                                })?;
-                                stats.removed_small += 1;
-                            }
-                        }
-                    }
+                    stats.removed_small += 1;
                 }
             }
         }
@@ -201,21 +578,233 @@ impl FileManager {
         // Ensure directory exists
         self.ensure_output_dir_exists().await?;
 
-        // Test if we can write to the directory
-        let test_file = self.output_dir.join(".test_write_permission");
+        if self.skip_write_check {
+            return Ok(());
+        }
 
-        match fs::write(&test_file, "test").await {
-            Ok(_) => {
-                // Clean up test file
-                if let Err(e) = fs::remove_file(&test_file).await {
-                    eprintln!("Warning: Failed to clean up test file: {e}");
-                }
-                Ok(())
-            }
-            Err(e) => Err(ScrapperError::file_system(
-                format!("Output directory is not writable: {e}"),
-                Some(self.output_dir.clone()),
-            )),
+        // Test if we can write to the directory using a probe name unique to this process
+        let probe = WriteProbe::new(self.output_dir.join(self.write_probe_file_name()));
+
+        fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&probe.path)
+            .await
+            .map_err(|e| {
+                ScrapperError::file_system(
+                    format!("Output directory is not writable: {e}"),
+                    Some(self.output_dir.clone()),
+                )
+            })?;
+
+        // The probe is removed when it goes out of scope
+        Ok(())
+    }
+
+    fn write_probe_file_name(&self) -> String {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or_default();
+        format!("{}_{}_{nanos}", self.write_probe_prefix, std::process::id())
+    }
+}
+
+/// Removes the write-permission probe file on drop, including on early return
+struct WriteProbe {
+    path: PathBuf,
+}
+
+impl WriteProbe {
+    fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+impl Drop for WriteProbe {
+    fn drop(&mut self) {
+        if self.path.exists()
+            && let Err(e) = std::fs::remove_file(&self.path)
+        {
+            eprintln!(
+                "Warning: Failed to clean up write probe {:?}: {e}",
+                self.path
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TempDir;
+
+    #[test]
+    fn test_volume_text_marks_missing_chapters() {
+        let text = volume_text(&[
+            ("1", Some("First chapter.\n".to_string())),
+            ("2", None),
+            ("3", Some("Third chapter.".to_string())),
+        ]);
+
+        assert_eq!(
+            text,
+            "===== Chapter 1 =====\n\nFirst chapter.\n\n\
+             ===== Chapter 2 =====\n\n[Chapter 2 is missing: it was not scraped successfully]\n\n\
+             ===== Chapter 3 =====\n\nThird chapter.\n\n"
+        );
+    }
+
+    #[test]
+    fn test_uniform_size_cluster_flags_near_identical_sizes() {
+        let sizes = [1200, 1201, 1199, 1200, 5400, 8100, 1203, 2500];
+        assert_eq!(
+            uniform_size_cluster(&sizes),
+            Some(SizeCluster {
+                size: 1200,
+                count: 5,
+                total: 8,
+            })
+        );
+    }
+
+    #[test]
+    fn test_uniform_size_cluster_ignores_varied_or_few_files() {
+        assert_eq!(
+            uniform_size_cluster(&[1000, 2000, 3000, 4000, 5000, 6000]),
+            None
+        );
+        assert_eq!(uniform_size_cluster(&[500, 500, 500]), None);
+    }
+
+    #[test]
+    fn test_bounded_file_name_keeps_short_names() {
+        assert_eq!(
+            bounded_file_name("chapter_12", Some("txt"), 200),
+            "chapter_12.txt"
+        );
+        assert_eq!(bounded_file_name("chapter_12", None, 200), "chapter_12");
+    }
+
+    #[test]
+    fn test_bounded_file_name_truncates_with_hash() {
+        let long = format!("chapter_{}", "é".repeat(150));
+        let name = bounded_file_name(&long, Some("txt"), 64);
+
+        assert!(name.len() <= 64, "{name}");
+        assert!(name.ends_with(".txt"));
+        assert!(name.starts_with("chapter_é"));
+
+        // Names sharing a long prefix stay distinct
+        let other = format!("{long}x");
+        assert_ne!(name, bounded_file_name(&other, Some("txt"), 64));
+    }
+
+    #[tokio::test]
+    async fn test_untracked_chapter_files() {
+        let dir = TempDir::new("untracked");
+        for name in [
+            "chapter_1.txt",
+            "chapter_2.txt",
+            "chapter_notes.txt",
+            "links.csv",
+        ] {
+            fs::write(dir.join(name), "text").await.unwrap();
+        }
+        let file_manager = FileManager::new(&Config {
+            output_dir: dir.path().to_path_buf(),
+            ..Config::default()
+        });
+        let records: Vec<ChapterRecord> = ["1", "2", "3"]
+            .iter()
+            .map(|n| ChapterRecord::new(format!("https://example.com/{n}"), n.to_string()))
+            .collect();
+
+        let untracked = file_manager.untracked_chapter_files(&records).await;
+
+        assert_eq!(untracked.unwrap(), [dir.join("chapter_notes.txt")]);
+    }
+
+    fn probe_manager(
+        dir: &TempDir,
+        write_probe_prefix: &str,
+        skip_write_check: bool,
+    ) -> FileManager {
+        FileManager::new(&Config {
+            output_dir: dir.path().to_path_buf(),
+            write_probe_prefix: write_probe_prefix.to_string(),
+            skip_write_check,
+            ..Config::default()
+        })
+    }
+
+    fn dir_entries(dir: &TempDir) -> Vec<PathBuf> {
+        std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect()
+    }
+
+    #[test]
+    fn test_write_probe_name_is_unique_to_the_process() {
+        let dir = TempDir::new("probe_name");
+        let name = probe_manager(&dir, "probe", false).write_probe_file_name();
+
+        let rest = name
+            .strip_prefix(&format!("probe_{}_", std::process::id()))
+            .unwrap_or_else(|| panic!("unexpected probe name {name}"));
+        assert!(rest.parse::<u32>().is_ok(), "{name}");
+    }
+
+    #[tokio::test]
+    async fn test_write_probe_is_removed() {
+        let dir = TempDir::new("probe_removed");
+
+        probe_manager(&dir, "probe", false)
+            .validate_output_dir()
+            .await
+            .unwrap();
+
+        assert!(dir_entries(&dir).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_failed_write_probe_leaves_nothing_behind() {
+        let dir = TempDir::new("probe_failed");
+        // Longer than any file system allows for a single name
+        let prefix = "p".repeat(300);
+
+        let result = probe_manager(&dir, &prefix, false)
+            .validate_output_dir()
+            .await;
+
+        assert!(matches!(result, Err(ScrapperError::FileSystem { .. })));
+        assert!(dir_entries(&dir).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_skip_write_check_bypasses_the_probe() {
+        let dir = TempDir::new("probe_skipped");
+        let prefix = "p".repeat(300);
+
+        probe_manager(&dir, &prefix, true)
+            .validate_output_dir()
+            .await
+            .unwrap();
+
+        assert!(dir_entries(&dir).is_empty());
+    }
+
+    #[test]
+    fn test_write_probe_prefix_must_be_a_file_name() {
+        let with_prefix = |write_probe_prefix: &str| Config {
+            write_probe_prefix: write_probe_prefix.to_string(),
+            ..Config::default()
+        };
+
+        assert!(with_prefix("scrapper_probe").validate().is_ok());
+        for invalid in ["", "  ", "probes/probe", "probes\\probe"] {
+            assert!(with_prefix(invalid).validate().is_err(), "{invalid:?}");
         }
     }
 }
@@ -0,0 +1,28 @@
+//! Concurrent chapter scraper: reads chapter links from a CSV file, fetches each page,
+//! extracts its text content and writes it to the output directory.
+
+pub mod app;
+pub mod config;
+pub mod cookies;
+pub mod csv_reader;
+pub mod discovery;
+pub mod error;
+pub mod file_manager;
+pub mod profile;
+pub mod progress;
+#[cfg(feature = "render")]
+pub mod render;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_sink;
+pub mod task_manager;
+#[cfg(test)]
+mod test_dir;
+#[cfg(test)]
+mod test_server;
+pub mod types;
+pub mod urls;
+pub mod verify;
+pub mod warc;
+pub mod web_scraper;
+
+pub use app::{Scrapper, ScrapperBuilder};
@@ -0,0 +1,98 @@
+//! `--profile`: wall-clock time spent in each phase of a run, printed at the end so slow
+//! phases (like scanning a large output directory) stand out.
+
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Phase durations in the order the phases finished
+pub struct RunProfile {
+    started: Instant,
+    phases: Mutex<Vec<(&'static str, Duration)>>,
+}
+
+impl Default for RunProfile {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RunProfile {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            phases: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Record a phase that began at `phase_started` and just finished
+    pub fn finish_phase(&self, name: &'static str, phase_started: Instant) {
+        self.record(name, phase_started.elapsed());
+    }
+
+    pub fn record(&self, name: &'static str, duration: Duration) {
+        if let Ok(mut phases) = self.phases.lock() {
+            phases.push((name, duration));
+        }
+    }
+
+    /// Breakdown of the recorded phases against the time since the profile was created
+    pub fn report(&self) -> String {
+        self.report_with_total(self.started.elapsed())
+    }
+
+    fn report_with_total(&self, total: Duration) -> String {
+        let phases = self.phases.lock().map(|p| p.clone()).unwrap_or_default();
+        let accounted: Duration = phases.iter().map(|(_, duration)| *duration).sum();
+        let share = |duration: Duration| {
+            if total.is_zero() {
+                0.0
+            } else {
+                duration.as_secs_f64() / total.as_secs_f64() * 100.0
+            }
+        };
+
+        let mut report = String::from("⏱️  Run profile:");
+        for (name, duration) in &phases {
+            report.push_str(&format!(
+                "\n   {name:<28} {:>9.3}s {:>5.1}%",
+                duration.as_secs_f64(),
+                share(*duration)
+            ));
+        }
+        let other = total.saturating_sub(accounted);
+        report.push_str(&format!(
+            "\n   {:<28} {:>9.3}s {:>5.1}%",
+            "Other",
+            other.as_secs_f64(),
+            share(other)
+        ));
+        report.push_str(&format!(
+            "\n   {:<28} {:>9.3}s",
+            "Total",
+            total.as_secs_f64()
+        ));
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_lists_phases_with_shares() {
+        let profile = RunProfile::new();
+        profile.record("CSV validation", Duration::from_millis(250));
+        profile.record("Scraping", Duration::from_millis(1500));
+
+        let report = profile.report_with_total(Duration::from_secs(2));
+        let lines: Vec<&str> = report.lines().collect();
+
+        assert_eq!(lines.len(), 5);
+        assert!(lines[1].contains("CSV validation") && lines[1].ends_with("12.5%"));
+        assert!(lines[2].contains("Scraping") && lines[2].ends_with("75.0%"));
+        assert!(lines[3].contains("Other") && lines[3].contains("0.250s"));
+        assert!(lines[4].contains("Total") && lines[4].ends_with("2.000s"));
+    }
+}
@@ -1,17 +1,144 @@
 use crate::error::{ScrapperError, ScrapperResult};
-use crate::types::ScrapingStats;
-use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use crate::types::{ChapterRecord, ScrapingStats};
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use serde::Serialize;
+use std::sync::Arc;
 use tokio::time::Duration;
 
+/// A live progress event, written as one JSON line to stderr with `--progress-json`
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ProgressEvent<'a> {
+    Started {
+        chapter: &'a str,
+        url: &'a str,
+        attempt: usize,
+    },
+    Finished {
+        chapter: &'a str,
+        url: &'a str,
+        bytes: u64,
+    },
+    Failed {
+        chapter: &'a str,
+        url: &'a str,
+        error: String,
+        recoverable: bool,
+        retrying: bool,
+    },
+    Skipped {
+        chapter: &'a str,
+        url: &'a str,
+        reason: &'a str,
+    },
+}
+
+/// Severity of a `log` line
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    Info,
+    Warning,
+    Error,
+}
+
+/// A message that would be printed above the progress bars, as a JSON line when they are
+/// hidden by `--progress-json`
+#[derive(Debug, Serialize)]
+struct LogLine<'a> {
+    event: &'static str,
+    level: LogLevel,
+    message: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    url: Option<&'a str>,
+}
+
+/// An event together with the run's counts at the time it happened
+#[derive(Debug, Serialize)]
+struct ProgressLine<'a> {
+    #[serde(flatten)]
+    event: ProgressEvent<'a>,
+    processed: u64,
+    total: u64,
+    success: usize,
+    errors: usize,
+}
+
+/// Receives each JSON line with `--progress-json`
+pub type JsonOutput = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// The JSON line for a log message
+fn log_line(level: LogLevel, message: &str, url: Option<&str>) -> String {
+    let line = LogLine {
+        event: "log",
+        level,
+        message,
+        url,
+    };
+    serde_json::to_string(&line).unwrap_or_else(|e| format!("Failed to serialize log message: {e}"))
+}
+
+/// Where a chapter task reports: its progress bar, or JSON lines when the bars are hidden
+#[derive(Clone)]
+pub struct ChapterLog {
+    bar: ProgressBar,
+    json_output: Option<JsonOutput>,
+}
+
+impl ChapterLog {
+    /// Show the step the chapter is at on its bar
+    pub fn set_step(&self, message: String) {
+        self.bar.set_message(message);
+    }
+
+    pub fn info(&self, icon: &str, message: &str) {
+        self.log(LogLevel::Info, icon, message);
+    }
+
+    pub fn warning(&self, icon: &str, message: &str) {
+        self.log(LogLevel::Warning, icon, message);
+    }
+
+    /// Print `message` above the bars with its icon, or as a JSON `log` line
+    fn log(&self, level: LogLevel, icon: &str, message: &str) {
+        match &self.json_output {
+            Some(output) => output(&log_line(level, message, None)),
+            None => self.bar.println(format!("{icon} {message}")),
+        }
+    }
+
+    pub fn finish_and_clear(&self) {
+        self.bar.finish_and_clear();
+    }
+}
+
 pub struct ProgressManager {
+    multi_progress: MultiProgress,
     main_pb: ProgressBar,
     stats_pb: ProgressBar,
     active_pb: ProgressBar,
+    json_output: Option<JsonOutput>,
 }
 
 impl ProgressManager {
-    pub fn new(total_records: u64) -> ScrapperResult<Self> {
-        let multi_progress = MultiProgress::new();
+    /// Create the progress display. With `json_events` the bars are hidden and
+    /// events are streamed to stderr as JSON lines instead.
+    pub fn new(total_records: u64, json_events: bool) -> ScrapperResult<Self> {
+        let stderr: JsonOutput = Arc::new(|line: &str| eprintln!("{line}"));
+        Self::with_json_output(total_records, json_events.then_some(stderr))
+    }
+
+    /// Create the progress display, hiding the bars and handing events to `json_output` as
+    /// JSON lines when it is set
+    pub fn with_json_output(
+        total_records: u64,
+        json_output: Option<JsonOutput>,
+    ) -> ScrapperResult<Self> {
+        let multi_progress = if json_output.is_some() {
+            MultiProgress::with_draw_target(ProgressDrawTarget::hidden())
+        } else {
+            MultiProgress::new()
+        };
 
         // Main progress bar
         let main_pb = multi_progress.add(ProgressBar::new(total_records));
@@ -54,17 +181,94 @@ impl ProgressManager {
         active_pb.enable_steady_tick(Duration::from_millis(200));
 
         Ok(Self {
+            multi_progress,
             main_pb,
             stats_pb,
             active_pb,
-            // multi_progress,
+            json_output,
         })
     }
 
+    /// Write an event as a JSON line to stderr when JSON progress is enabled
+    pub fn emit(&self, event: ProgressEvent<'_>, stats: &ScrapingStats) {
+        let Some(output) = &self.json_output else {
+            return;
+        };
+
+        let line = ProgressLine {
+            event,
+            processed: self.main_pb.position(),
+            total: self.main_pb.length().unwrap_or(0),
+            success: stats.success_count,
+            errors: stats.error_count,
+        };
+        match serde_json::to_string(&line) {
+            Ok(json) => output(&json),
+            Err(e) => eprintln!("Failed to serialize progress event: {e}"),
+        }
+    }
+
+    pub fn chapter_started(&self, record: &ChapterRecord, attempt: usize, stats: &ScrapingStats) {
+        self.emit(
+            ProgressEvent::Started {
+                chapter: &record.chapter_number,
+                url: &record.url,
+                attempt,
+            },
+            stats,
+        );
+    }
+
+    pub fn chapter_finished(&self, record: &ChapterRecord, bytes: u64, stats: &ScrapingStats) {
+        self.emit(
+            ProgressEvent::Finished {
+                chapter: &record.chapter_number,
+                url: &record.url,
+                bytes,
+            },
+            stats,
+        );
+    }
+
+    pub fn chapter_failed(
+        &self,
+        record: &ChapterRecord,
+        error: &ScrapperError,
+        retrying: bool,
+        stats: &ScrapingStats,
+    ) {
+        self.emit(
+            ProgressEvent::Failed {
+                chapter: &record.chapter_number,
+                url: &record.url,
+                error: error.user_friendly_message(),
+                recoverable: error.is_recoverable(),
+                retrying,
+            },
+            stats,
+        );
+    }
+
+    pub fn chapter_skipped(&self, record: &ChapterRecord, reason: &str, stats: &ScrapingStats) {
+        self.emit(
+            ProgressEvent::Skipped {
+                chapter: &record.chapter_number,
+                url: &record.url,
+                reason,
+            },
+            stats,
+        );
+    }
+
     pub fn increment_progress(&self) {
         self.main_pb.inc(1);
     }
 
+    /// Shrink the total to what was processed, for runs that stop before every record
+    pub fn set_total_to_processed(&self) {
+        self.main_pb.set_length(self.main_pb.position());
+    }
+
     pub fn update_active_tasks(&self, active_count: usize) {
         self.active_pb.set_message(format!("{active_count} tasks"));
     }
@@ -83,15 +287,44 @@ impl ProgressManager {
         ));
     }
 
+    /// Print a message above the progress bars, or as a JSON line with `--progress-json`,
+    /// since hidden bars drop what is printed on them. `display` is the decorated text shown
+    /// above the bars.
+    fn log(&self, level: LogLevel, message: &str, display: String) {
+        match &self.json_output {
+            Some(output) => output(&log_line(level, message, None)),
+            None => self.stats_pb.println(display),
+        }
+    }
+
+    /// A handle for a chapter task to report on `bar`, following this display's JSON setting
+    pub fn chapter_log(&self, bar: ProgressBar) -> ChapterLog {
+        ChapterLog {
+            bar,
+            json_output: self.json_output.clone(),
+        }
+    }
+
     pub fn log_error(&self, error: &ScrapperError) {
-        // Use user-friendly message for display
-        let message = if error.is_recoverable() {
-            format!("⚠️  Recoverable error: {}", error.user_friendly_message())
+        let message = error.user_friendly_message();
+        let level = if error.is_recoverable() {
+            LogLevel::Warning
         } else {
-            format!("❌ Error: {}", error.user_friendly_message())
+            LogLevel::Error
         };
 
-        self.stats_pb.println(message);
+        if let Some(output) = &self.json_output {
+            output(&log_line(level, &message, error.url()));
+            return;
+        }
+
+        // Use user-friendly message for display
+        if error.is_recoverable() {
+            self.stats_pb
+                .println(format!("⚠️  Recoverable error: {message}"));
+        } else {
+            self.stats_pb.println(format!("❌ Error: {message}"));
+        }
 
         // Log debug info if available
         if let Some(url) = error.url() {
@@ -100,16 +333,21 @@ impl ProgressManager {
     }
 
     pub fn log_skip(&self, file_name: &str) {
-        self.stats_pb
-            .println(format!("⏭️ Skipping existing file: {file_name}"));
+        let message = format!("Skipping existing file: {file_name}");
+        self.log(LogLevel::Info, &message, format!("⏭️ {message}"));
+    }
+
+    pub fn log_filtered(&self, chapter_number: &str, reason: &str) {
+        let message = format!("Skipping chapter {chapter_number}: {reason}");
+        self.log(LogLevel::Info, &message, format!("🚫 {message}"));
     }
 
     pub fn log_info(&self, message: &str) {
-        self.stats_pb.println(format!("ℹ️ {message}",));
+        self.log(LogLevel::Info, message, format!("ℹ️ {message}"));
     }
 
     pub fn log_warning(&self, message: &str) {
-        self.stats_pb.println(format!("⚠️ {message}"));
+        self.log(LogLevel::Warning, message, format!("⚠️ {message}"));
     }
 
     pub fn finish(&self, stats: &ScrapingStats) {
@@ -138,9 +376,44 @@ impl ProgressManager {
         println!("   ✅ Successful: {}", stats.success_count);
         println!("   ❌ Errors: {}", stats.error_count);
         println!("   📈 Success Rate: {:.1}%", stats.success_rate());
+        if stats.deferred > 0 {
+            println!("   ⏸️  Left for a later run: {}", stats.deferred);
+        }
+        if stats.kept_existing > 0 {
+            println!(
+                "   📎 Kept existing (new content not larger): {}",
+                stats.kept_existing
+            );
+        }
+        if stats.skipped_empty > 0 {
+            println!(
+                "   🈳 Skipped (no content after filtering): {}",
+                stats.skipped_empty
+            );
+        }
+        if stats.filtered_by_host > 0 {
+            println!("   🚫 Skipped by host filter: {}", stats.filtered_by_host);
+        }
+        if !stats.changed_chapters.is_empty() || stats.unchanged > 0 {
+            println!(
+                "   🔁 Refreshed: {} changed, {} unchanged",
+                stats.changed_chapters.len(),
+                stats.unchanged
+            );
+        }
+        if !stats.changed_chapters.is_empty() {
+            println!(
+                "   📝 Changed chapters: {}",
+                stats.changed_chapters.join(", ")
+            );
+        }
 
         if stats.error_count > 0 {
-            println!("\n💡 Tip: Check the error messages above for specific issues.");
+            if self.json_output.is_some() {
+                println!("\n💡 Tip: Check the error \"log\" events on stderr for specific issues.");
+            } else {
+                println!("\n💡 Tip: Check the error messages above for specific issues.");
+            }
             println!("   Common solutions:");
             println!("   • Increase delays if you're being rate-limited");
             println!("   • Check your internet connection for connection errors");
@@ -148,6 +421,17 @@ impl ProgressManager {
         }
     }
 
+    /// Add a progress line for one in-flight chapter; finish it with `finish_and_clear`
+    pub fn chapter_bar(&self, record: &ChapterRecord) -> ProgressBar {
+        let pb = self.multi_progress.add(ProgressBar::new_spinner());
+        if let Ok(style) = ProgressStyle::default_spinner().template("  {spinner:.yellow} {msg}") {
+            pb.set_style(style);
+        }
+        pb.set_message(format!("chapter {}: queued", record.chapter_number));
+        pb.enable_steady_tick(Duration::from_millis(120));
+        pb
+    }
+
     pub fn get_stats_pb(&self) -> ProgressBar {
         self.stats_pb.clone()
     }
@@ -164,3 +448,44 @@ impl ProgressManager {
         Ok(())
     }
 }
+
+/// A JSON output collecting the lines, for tests
+#[cfg(test)]
+pub(crate) fn capture_json_output() -> (JsonOutput, Arc<std::sync::Mutex<Vec<String>>>) {
+    let lines = Arc::new(std::sync::Mutex::new(Vec::new()));
+    let sink = Arc::clone(&lines);
+    let output: JsonOutput = Arc::new(move |line: &str| {
+        sink.lock().unwrap().push(line.to_string());
+    });
+    (output, lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_logs_are_json_lines_when_bars_are_hidden() {
+        let (output, lines) = capture_json_output();
+        let progress = ProgressManager::with_json_output(3, Some(output)).unwrap();
+
+        progress.log_warning("Stopping early");
+        progress.log_error(&ScrapperError::http(
+            "https://example.com/1",
+            Some(404),
+            "HTTP 404",
+        ));
+        progress
+            .chapter_log(progress.get_stats_pb())
+            .info("🔀", "Chapter 1 fetched from fallback URL");
+
+        let lines = lines.lock().unwrap();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[0],
+            r#"{"event":"log","level":"warning","message":"Stopping early"}"#
+        );
+        assert!(lines[1].ends_with(r#""url":"https://example.com/1"}"#));
+        assert!(lines[2].contains(r#""message":"Chapter 1 fetched from fallback URL""#));
+    }
+}
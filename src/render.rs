@@ -0,0 +1,144 @@
+//! Headless Chromium backend for `render = true`: loads pages in a real browser so content
+//! built client-side with JavaScript is present before extraction.
+
+use crate::error::{ScrapperError, ScrapperResult};
+use crate::types::Config;
+use chromiumoxide::Page;
+use chromiumoxide::browser::{Browser, BrowserConfig};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tokio::time::{Instant, sleep, timeout};
+use tokio_stream::StreamExt;
+
+/// How often the page is checked for `wait_for_selector`
+const SELECTOR_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A browser shared by all scraping tasks, with one tab opened per page
+pub struct PageRenderer {
+    browser: Browser,
+    handler: JoinHandle<()>,
+    user_agent: String,
+    page_timeout: Duration,
+    wait_for_selector: Option<String>,
+    wait_timeout: Duration,
+    settle: Duration,
+}
+
+impl PageRenderer {
+    /// Launch a headless browser found on the system (Chrome or Chromium)
+    pub async fn launch(config: &Config) -> ScrapperResult<Self> {
+        let browser_config = BrowserConfig::builder()
+            .request_timeout(Duration::from_secs(config.request_timeout_secs))
+            .build()
+            .map_err(|e| {
+                ScrapperError::config(format!(
+                    "Can't set up the headless browser for `render`: {e}"
+                ))
+            })?;
+
+        let (browser, mut handler) = Browser::launch(browser_config).await.map_err(|e| {
+            ScrapperError::config(format!(
+                "Failed to launch Chrome/Chromium for `render`: {e}"
+            ))
+        })?;
+
+        // The handler drives the DevTools connection and must be polled for the browser to work
+        let handler = tokio::spawn(async move {
+            while let Some(event) = handler.next().await {
+                if event.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self {
+            browser,
+            handler,
+            user_agent: config.user_agent.clone(),
+            page_timeout: Duration::from_secs(config.request_timeout_secs),
+            wait_for_selector: config.wait_for_selector.clone(),
+            wait_timeout: Duration::from_millis(config.wait_timeout_ms),
+            settle: Duration::from_millis(config.render_settle_ms),
+        })
+    }
+
+    /// Load a page and return its DOM serialized after scripts have run
+    pub async fn render(&self, url: &str) -> ScrapperResult<String> {
+        let page = self
+            .browser
+            .new_page("about:blank")
+            .await
+            .map_err(|e| ScrapperError::render(url, format!("Failed to open a tab: {e}")))?;
+
+        let rendered = self.load(&page, url).await;
+
+        // Close the tab whatever happened, so failed pages don't pile up in the browser
+        let _ = page.close().await;
+
+        rendered
+    }
+
+    /// Navigate a tab to `url`, wait for `wait_for_selector` if set and then `render_settle_ms`,
+    /// and serialize the DOM
+    async fn load(&self, page: &Page, url: &str) -> ScrapperResult<String> {
+        let navigation = timeout(self.page_timeout, async {
+            page.set_user_agent(self.user_agent.as_str()).await?;
+            page.goto(url).await?;
+            page.wait_for_navigation().await?;
+            Ok::<_, chromiumoxide::error::CdpError>(())
+        })
+        .await;
+
+        match navigation {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => return Err(ScrapperError::render(url, e.to_string())),
+            Err(_) => {
+                return Err(ScrapperError::render(
+                    url,
+                    format!(
+                        "Page did not finish loading within {} seconds",
+                        self.page_timeout.as_secs()
+                    ),
+                ));
+            }
+        }
+
+        if let Some(selector) = &self.wait_for_selector {
+            self.wait_for(page, url, selector).await?;
+        }
+
+        // Lazy-loaded parts may still be arriving after the page or selector is ready
+        if !self.settle.is_zero() {
+            sleep(self.settle).await;
+        }
+
+        page.content()
+            .await
+            .map_err(|e| ScrapperError::render(url, e.to_string()))
+    }
+
+    /// Poll until an element matches `selector`, for content loaded after the page itself
+    async fn wait_for(&self, page: &Page, url: &str, selector: &str) -> ScrapperResult<()> {
+        let deadline = Instant::now() + self.wait_timeout;
+
+        loop {
+            if page.find_element(selector).await.is_ok() {
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                return Err(ScrapperError::selector_timeout(
+                    url,
+                    selector,
+                    self.wait_timeout.as_millis() as u64,
+                ));
+            }
+            sleep(SELECTOR_POLL_INTERVAL).await;
+        }
+    }
+}
+
+impl Drop for PageRenderer {
+    fn drop(&mut self) {
+        self.handler.abort();
+    }
+}
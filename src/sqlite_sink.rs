@@ -0,0 +1,163 @@
+//! SQLite output for `sqlite_database`: chapters are upserted into a `chapters` table instead
+//! of being written as text files, so the library is queryable right away.
+
+use crate::error::{ScrapperError, ScrapperResult};
+use rusqlite::{Connection, OptionalExtension, params};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How long a write waits for another connection to the same database before failing
+const BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+
+const CREATE_CHAPTERS_TABLE: &str = "CREATE TABLE IF NOT EXISTS chapters (
+    chapter_number TEXT PRIMARY KEY,
+    url TEXT NOT NULL,
+    title TEXT,
+    content TEXT NOT NULL,
+    fetched_at TEXT NOT NULL
+)";
+
+/// One scraped chapter as stored in the `chapters` table
+#[derive(Debug, Clone)]
+pub struct ChapterRow {
+    pub chapter_number: String,
+    pub url: String,
+    pub title: Option<String>,
+    pub content: String,
+}
+
+/// A connection to the chapters database, cheap to clone and shared by all tasks
+#[derive(Clone)]
+pub struct SqliteSink {
+    path: PathBuf,
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl SqliteSink {
+    /// Open (or create) the database and make sure the `chapters` table exists
+    pub fn open(path: &Path) -> ScrapperResult<Self> {
+        let db_error = |e: rusqlite::Error| {
+            ScrapperError::file_system(
+                format!("Failed to open SQLite database: {e}"),
+                Some(path.to_path_buf()),
+            )
+        };
+
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent).map_err(|e| {
+                ScrapperError::file_system(
+                    format!("Failed to create directory for SQLite database: {e}"),
+                    Some(parent.to_path_buf()),
+                )
+            })?;
+        }
+
+        let connection = Connection::open(path).map_err(db_error)?;
+        connection.busy_timeout(BUSY_TIMEOUT).map_err(db_error)?;
+        connection
+            .execute(CREATE_CHAPTERS_TABLE, [])
+            .map_err(db_error)?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    /// Whether a chapter with non-empty content is already stored. A failed lookup counts as
+    /// missing, so the chapter is scraped again and upserted.
+    pub fn contains(&self, chapter_number: &str) -> bool {
+        let Ok(connection) = self.connection.lock() else {
+            return false;
+        };
+
+        connection
+            .query_row(
+                "SELECT 1 FROM chapters WHERE chapter_number = ?1 AND length(content) > 0",
+                params![chapter_number],
+                |_| Ok(()),
+            )
+            .optional()
+            .is_ok_and(|row| row.is_some())
+    }
+
+    /// Insert a chapter, replacing any earlier version of it
+    pub async fn upsert(&self, row: ChapterRow) -> ScrapperResult<()> {
+        let connection = Arc::clone(&self.connection);
+        let path = self.path.clone();
+        let fetched_at = chrono::Utc::now().to_rfc3339();
+
+        let result = tokio::task::spawn_blocking(move || {
+            let connection = connection
+                .lock()
+                .map_err(|_| "database connection poisoned by a panicked task".to_string())?;
+            connection
+                .execute(
+                    "INSERT INTO chapters (chapter_number, url, title, content, fetched_at)
+                     VALUES (?1, ?2, ?3, ?4, ?5)
+                     ON CONFLICT(chapter_number) DO UPDATE SET
+                         url = excluded.url,
+                         title = excluded.title,
+                         content = excluded.content,
+                         fetched_at = excluded.fetched_at",
+                    params![
+                        row.chapter_number,
+                        row.url,
+                        row.title,
+                        row.content,
+                        fetched_at
+                    ],
+                )
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        })
+        .await
+        .map_err(|e| ScrapperError::task_execution(format!("SQLite write task failed: {e}")))?;
+
+        result.map_err(|message| {
+            ScrapperError::file_system(
+                format!("Failed to store chapter in SQLite database: {message}"),
+                Some(path),
+            )
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TempDir;
+
+    #[tokio::test]
+    async fn test_upsert_replaces_chapter() {
+        let dir = TempDir::new("sqlite_sink");
+        let sink = SqliteSink::open(&dir.join("library.db")).unwrap();
+
+        assert!(!sink.contains("1"));
+        for content in ["first version", "second version"] {
+            sink.upsert(ChapterRow {
+                chapter_number: "1".to_string(),
+                url: "https://example.com/1".to_string(),
+                title: Some("Chapter 1".to_string()),
+                content: content.to_string(),
+            })
+            .await
+            .unwrap();
+        }
+
+        assert!(sink.contains("1"));
+        assert!(!sink.contains("2"));
+        let (count, content): (i64, String) = sink
+            .connection
+            .lock()
+            .unwrap()
+            .query_row("SELECT count(*), max(content) FROM chapters", [], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .unwrap();
+        assert_eq!((count, content.as_str()), (1, "second version"));
+    }
+}
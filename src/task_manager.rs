@@ -1,33 +1,65 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
 use tokio::task::JoinSet;
+use tokio::time::{Instant, sleep_until};
 
 pub struct TaskManager<T> {
     join_set: JoinSet<T>,
     max_concurrent: usize,
+    // Minimum time between two task starts, if the start rate is limited
+    start_interval: Option<Duration>,
+    last_start: Option<Instant>,
 }
 impl<T: 'static> TaskManager<T> {
     pub fn new(max_concurrent: usize) -> Self {
         Self {
             join_set: JoinSet::new(),
             max_concurrent,
+            start_interval: None,
+            last_start: None,
         }
     }
+
+    /// Start at most `max_starts_per_sec` tasks per second, however many are in flight
+    pub fn with_max_starts_per_sec(mut self, max_starts_per_sec: Option<f64>) -> Self {
+        self.start_interval = max_starts_per_sec.map(|rate| Duration::from_secs_f64(1.0 / rate));
+        self
+    }
     pub async fn spawn_or_wait<F, Fut>(&mut self, task: F) -> Option<T>
     where
         F: FnOnce() -> Fut,
         Fut: Future<Output = T> + Send + 'static,
         T: Send + 'static,
     {
-        // If we're at capacity, wait for one task to complete
-        if self.join_set.len() >= self.max_concurrent {
-            // Wait for any task to complete and return its result
-            return self.join_set.join_next().await.and_then(|res| res.ok());
+        // If we're at capacity, wait for one task to complete before spawning
+        let completed = if self.join_set.len() >= self.max_concurrent {
+            self.join_set.join_next().await.and_then(|res| res.ok())
+        } else {
+            None
+        };
+
+        // Keep to the start rate, counting from the previous start
+        if let (Some(interval), Some(last_start)) = (self.start_interval, self.last_start) {
+            sleep_until(last_start + interval).await;
         }
+        self.last_start = Some(Instant::now());
 
         // We have capacity, spawn the new task
         let future = task();
         self.join_set.spawn(future);
 
-        // No completed task to return yet
+        // Result of the task that made room, if we had to wait
+        completed
+    }
+
+    /// Wait for the next task to complete; `None` once no tasks are running
+    pub async fn join_next(&mut self) -> Option<T> {
+        while let Some(result) = self.join_set.join_next().await {
+            if let Ok(value) = result {
+                return Some(value);
+            }
+        }
         None
     }
 
@@ -52,3 +84,79 @@ impl<T: 'static> TaskManager<T> {
         self.join_set.is_empty()
     }
 }
+
+/// Run-wide cap on retry attempts, shared between all tasks
+#[derive(Debug, Clone)]
+pub struct RetryBudget {
+    // `None` means unlimited
+    remaining: Option<Arc<AtomicUsize>>,
+}
+
+impl RetryBudget {
+    pub fn new(max_total_retries: Option<usize>) -> Self {
+        Self {
+            remaining: max_total_retries.map(|max| Arc::new(AtomicUsize::new(max))),
+        }
+    }
+
+    /// Take one retry from the budget, returning false once it is exhausted
+    pub fn try_acquire(&self) -> bool {
+        match &self.remaining {
+            Some(remaining) => remaining
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |left| {
+                    left.checked_sub(1)
+                })
+                .is_ok(),
+            None => true,
+        }
+    }
+}
+
+/// Why an item was not queued for another attempt
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryRefusal {
+    MaxRetriesExceeded,
+    BudgetExhausted,
+}
+
+/// Items waiting for another attempt, bounded per item and by the run-wide budget
+pub struct RetryQueue<T> {
+    pending: Vec<(T, usize)>,
+    max_retries: usize,
+    budget: RetryBudget,
+}
+
+impl<T> RetryQueue<T> {
+    pub fn new(max_retries: usize, budget: RetryBudget) -> Self {
+        Self {
+            pending: Vec::new(),
+            max_retries,
+            budget,
+        }
+    }
+
+    /// Queue an item that has already been retried `retries_done` times
+    pub fn schedule(&mut self, item: T, retries_done: usize) -> Result<(), RetryRefusal> {
+        if retries_done >= self.max_retries {
+            return Err(RetryRefusal::MaxRetriesExceeded);
+        }
+        if !self.budget.try_acquire() {
+            return Err(RetryRefusal::BudgetExhausted);
+        }
+        self.pending.push((item, retries_done + 1));
+        Ok(())
+    }
+
+    /// Next item to retry, with the number of retries it is on
+    pub fn pop(&mut self) -> Option<(T, usize)> {
+        self.pending.pop()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
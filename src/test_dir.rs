@@ -0,0 +1,33 @@
+//! A scratch directory for tests that is removed again even when an assertion panics.
+
+use std::path::{Path, PathBuf};
+
+/// `scrapper_<name>_<pid>` under the system temp directory, deleted with its contents on drop
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    /// Create a fresh, empty directory; `name` must be unique among the tests
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("scrapper_{name}_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// A path inside the directory
+    pub fn join(&self, name: impl AsRef<Path>) -> PathBuf {
+        self.path.join(name)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+    }
+}
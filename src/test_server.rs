@@ -0,0 +1,41 @@
+//! A bare HTTP server for tests that need a real response without a mock server dependency.
+
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::sync::mpsc::{self, Receiver};
+
+/// Serve one response on a local port, then stop, so a second request fails to connect.
+/// Returns the server's base URL, like `http://127.0.0.1:1234`.
+pub fn serve_once(status: &str, headers: &[(&str, &str)], body: &str) -> String {
+    serve_once_recording(status, headers, body).0
+}
+
+/// Like [`serve_once`], also handing back the raw request the server received
+pub fn serve_once_recording(
+    status: &str,
+    headers: &[(&str, &str)],
+    body: &str,
+) -> (String, Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+
+    let mut response = format!("HTTP/1.1 {status}\r\n");
+    for (name, value) in headers {
+        response.push_str(&format!("{name}: {value}\r\n"));
+    }
+    response.push_str(&format!(
+        "Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    ));
+
+    let (sender, receiver) = mpsc::channel();
+    std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = [0; 4096];
+        let read = stream.read(&mut request).unwrap_or(0);
+        let _ = sender.send(String::from_utf8_lossy(&request[..read]).into_owned());
+        stream.write_all(response.as_bytes()).unwrap();
+    });
+
+    (format!("http://{address}"), receiver)
+}
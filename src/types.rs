@@ -1,4 +1,14 @@
-use crate::error::{ScrapperError, ScrapperResult};
+use crate::error::{ErrorKind, ScrapperError, ScrapperResult};
+use crate::urls;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::time::Duration;
+
+/// Number of slowest and fastest chapter attempts kept for the summary
+const TIMING_EXTREMES: usize = 5;
+
+/// Weight of the latest attempt in the success rate moving average (roughly the last 20 attempts)
+const SUCCESS_EMA_ALPHA: f64 = 0.1;
 
 #[derive(Debug, Clone)]
 pub struct ChapterRecord {
@@ -14,25 +24,19 @@ impl ChapterRecord {
         }
     }
 
-    pub fn file_name(&self) -> String {
-        format!("chapter_{}.txt", self.chapter_number)
+    /// Base name shared by the chapter's file and bundle folder
+    pub fn base_name(&self) -> String {
+        format!("chapter_{}", self.chapter_number)
     }
 
     /// Validate the chapter record
-    pub fn validate(&self) -> ScrapperResult<()> {
+    pub fn validate(&self, allowed_schemes: &[String]) -> ScrapperResult<()> {
         if self.url.is_empty() {
             return Err(ScrapperError::validation("url", "URL cannot be empty"));
         }
 
-        if !self.url.starts_with("http://") && !self.url.starts_with("https://") {
-            return Err(ScrapperError::validation(
-                "url",
-                format!(
-                    "Invalid URL format: '{}'. URLs must start with http:// or https://",
-                    self.url
-                ),
-            ));
-        }
+        urls::validate_url(&self.url, allowed_schemes)
+            .map_err(|message| ScrapperError::validation("url", message))?;
 
         if self.chapter_number.is_empty() {
             return Err(ScrapperError::validation(
@@ -59,14 +63,88 @@ impl ChapterRecord {
     }
 }
 
+/// How a chapter re-fetched with `existing_policy = "refresh"` differs from the file on disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefreshChange {
+    Unchanged,
+    /// Lines only in the new version and lines only in the previous one; both are zero when
+    /// lines were only reordered or their line endings changed
+    Changed {
+        added: usize,
+        removed: usize,
+    },
+}
+
+impl RefreshChange {
+    /// Compare the saved version of a chapter with newly extracted content
+    pub fn between(previous: &[u8], new: &str) -> Self {
+        if Sha256::digest(previous) == Sha256::digest(new.as_bytes()) {
+            return Self::Unchanged;
+        }
+
+        // Lines are compared as multisets, so moved lines don't count as changes
+        let previous = String::from_utf8_lossy(previous);
+        let mut balance: HashMap<&str, isize> = HashMap::new();
+        for line in new.lines() {
+            *balance.entry(line).or_default() += 1;
+        }
+        for line in previous.lines() {
+            *balance.entry(line).or_default() -= 1;
+        }
+
+        let added = balance.values().filter(|n| **n > 0).sum::<isize>() as usize;
+        let removed = balance
+            .values()
+            .filter(|n| **n < 0)
+            .map(|n| -n)
+            .sum::<isize>() as usize;
+        Self::Changed { added, removed }
+    }
+}
+
+impl std::fmt::Display for RefreshChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unchanged => write!(f, "unchanged"),
+            Self::Changed {
+                added: 0,
+                removed: 0,
+            } => write!(f, "lines reordered or line endings changed"),
+            Self::Changed { added, removed } => write!(f, "+{added}/-{removed} lines"),
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone)]
 pub struct ScrapingStats {
     pub total: usize,
     pub existing: usize,
+    /// Records with a non-empty chapter file already saved, and those files' total size,
+    /// noted while counting `existing` for the pre-run size estimate
+    pub saved_files: usize,
+    pub saved_bytes: u64,
     pub success_count: usize,
     pub error_count: usize,
     pub recoverable_errors: usize,
     pub permanent_errors: usize,
+    pub retry_budget_exhausted: usize,
+    pub filtered_by_host: usize,
+    pub deferred: usize,
+    pub kept_existing: usize,
+    /// Chapters with no content left after filtering, skipped by `empty_content_policy`
+    pub skipped_empty: usize,
+    /// Chapters whose content differed from the existing file in refresh mode
+    pub changed_chapters: Vec<String>,
+    /// Chapters re-fetched in refresh mode with the same content as the existing file
+    pub unchanged: usize,
+    /// Final failures by error variant; sums to `error_count`
+    pub errors_by_kind: BTreeMap<ErrorKind, usize>,
+    pub timings: ChapterTimings,
+    /// Cumulative time spent in scrape attempts per host
+    pub host_runtime: HashMap<String, Duration>,
+    /// Exponential moving average of attempt outcomes (1.0 = success), once any attempt finished
+    pub success_ema: Option<f64>,
+    pub attempts: usize,
 }
 
 impl ScrapingStats {
@@ -78,14 +156,82 @@ impl ScrapingStats {
         self.success_count += 1;
     }
 
-    pub fn increment_recoverable_error(&mut self) {
+    pub fn increment_recoverable_error(&mut self, kind: ErrorKind) {
         self.error_count += 1;
         self.recoverable_errors += 1;
+        *self.errors_by_kind.entry(kind).or_default() += 1;
     }
 
-    pub fn increment_permanent_error(&mut self) {
+    pub fn increment_permanent_error(&mut self, kind: ErrorKind) {
         self.error_count += 1;
         self.permanent_errors += 1;
+        *self.errors_by_kind.entry(kind).or_default() += 1;
+    }
+
+    /// A recoverable error given up on because the run-wide retry budget ran out
+    pub fn increment_budget_exhausted_error(&mut self, kind: ErrorKind) {
+        self.increment_permanent_error(kind);
+        self.retry_budget_exhausted += 1;
+    }
+
+    /// Record how a chapter re-fetched in refresh mode compared with its previous version
+    pub fn record_refresh(&mut self, chapter_number: &str, change: RefreshChange) {
+        match change {
+            RefreshChange::Unchanged => self.unchanged += 1,
+            RefreshChange::Changed { .. } => self.changed_chapters.push(chapter_number.to_string()),
+        }
+    }
+
+    /// Final failures getting pages (HTTP, connection, rendering)
+    pub fn network_errors(&self) -> usize {
+        self.count_errors(ErrorKind::is_network)
+    }
+
+    /// Final failures finding content in pages that loaded
+    pub fn extraction_errors(&self) -> usize {
+        self.count_errors(ErrorKind::is_extraction)
+    }
+
+    fn count_errors(&self, matches: impl Fn(ErrorKind) -> bool) -> usize {
+        self.errors_by_kind
+            .iter()
+            .filter(|(kind, _)| matches(**kind))
+            .map(|(_, count)| count)
+            .sum()
+    }
+
+    /// Record the outcome of a scrape attempt: its duration and the success rate moving average
+    pub fn record_attempt(&mut self, record: &ChapterRecord, duration: Duration, succeeded: bool) {
+        let outcome = if succeeded { 1.0 } else { 0.0 };
+        self.success_ema = Some(match self.success_ema {
+            Some(ema) => ema + SUCCESS_EMA_ALPHA * (outcome - ema),
+            None => outcome,
+        });
+        self.attempts += 1;
+
+        if let Some(host) = urls::host_of(&record.url) {
+            *self.host_runtime.entry(host).or_default() += duration;
+        }
+
+        self.timings.record(ChapterTiming {
+            chapter_number: record.chapter_number.clone(),
+            url: record.url.clone(),
+            duration,
+            succeeded,
+        });
+    }
+
+    /// Time spent so far scraping the host of `url`
+    pub fn runtime_for_host_of(&self, url: &str) -> Duration {
+        urls::host_of(url)
+            .and_then(|host| self.host_runtime.get(&host).copied())
+            .unwrap_or_default()
+    }
+
+    /// Recent success rate (percent) if it dropped below `threshold` after at least `min_samples` attempts
+    pub fn collapsed_success_rate(&self, threshold: f64, min_samples: usize) -> Option<f64> {
+        let recent = self.success_ema? * 100.0;
+        (self.attempts >= min_samples && recent < threshold).then_some(recent)
     }
 
     pub fn success_rate(&self) -> f64 {
@@ -124,7 +270,7 @@ impl ScrapingStats {
 
     /// Get a summary report of the scraping statistics
     pub fn summary_report(&self) -> String {
-        format!(
+        let mut report = format!(
             "Scraping Summary:
   📊 Total Records: {}
   📁 Already Existing: {}
@@ -144,7 +290,67 @@ impl ScrapingStats {
             self.success_rate(),
             self.error_rate(),
             self.completion_rate()
-        )
+        );
+
+        if !self.errors_by_kind.is_empty() {
+            report.push_str("\n  🧩 Errors by kind:");
+            for (kind, count) in &self.errors_by_kind {
+                report.push_str(&format!("\n    └── {kind}: {count}"));
+            }
+        }
+
+        if self.deferred > 0 {
+            report.push_str(&format!("\n  ⏸️ Left for a later run: {}", self.deferred));
+        }
+
+        if self.kept_existing > 0 {
+            report.push_str(&format!(
+                "\n  📎 Kept existing (new content not larger): {}",
+                self.kept_existing
+            ));
+        }
+
+        if self.skipped_empty > 0 {
+            report.push_str(&format!(
+                "\n  🈳 Skipped (no content after filtering): {}",
+                self.skipped_empty
+            ));
+        }
+
+        if !self.changed_chapters.is_empty() || self.unchanged > 0 {
+            report.push_str(&format!(
+                "\n  🔁 Refreshed: {} changed, {} unchanged",
+                self.changed_chapters.len(),
+                self.unchanged
+            ));
+        }
+
+        if self.filtered_by_host > 0 {
+            report.push_str(&format!(
+                "\n  🚫 Skipped by host filter: {}",
+                self.filtered_by_host
+            ));
+        }
+
+        if self.retry_budget_exhausted > 0 {
+            report.push_str(&format!(
+                "\n  ⛔ Not retried (retry budget exhausted): {}",
+                self.retry_budget_exhausted
+            ));
+        }
+
+        if !self.timings.is_empty() {
+            report.push_str("\n  🐢 Slowest chapters:");
+            for timing in self.timings.slowest() {
+                report.push_str(&format!("\n    └── {timing}"));
+            }
+            report.push_str("\n  🐇 Fastest chapters:");
+            for timing in self.timings.fastest() {
+                report.push_str(&format!("\n    └── {timing}"));
+            }
+        }
+
+        report
     }
 
     /// Get recommendations based on the statistics
@@ -164,13 +370,32 @@ impl ScrapingStats {
                 .push("Many permanent errors detected. Check URLs and CSS selectors.".to_string());
         }
 
+        let network_errors = self.network_errors();
+        let extraction_errors = self.extraction_errors();
+        if network_errors > extraction_errors && network_errors * 2 > self.error_count {
+            recommendations.push(format!(
+                "Most failures ({network_errors}) were HTTP/network errors. Increase delays, lower concurrency, or check the site is reachable."
+            ));
+        } else if extraction_errors > network_errors && extraction_errors * 2 > self.error_count {
+            recommendations.push(format!(
+                "Most failures ({extraction_errors}) were content extraction errors: pages loaded but no content was found. Check the CSS selector and skip_text_nodes."
+            ));
+        }
+
         if self.success_count == 0 && self.error_count > 0 {
             recommendations.push("No successful scrapes. Check your configuration, network connection, and target URLs.".to_string());
         }
 
+        if self.retry_budget_exhausted > 0 {
+            recommendations.push(format!(
+                "The retry budget ran out; {} chapters were not retried. Re-run later or raise max_total_retries.",
+                self.retry_budget_exhausted
+            ));
+        }
+
         if self.total > 1000 && self.success_rate() < 95.0 {
             recommendations.push(
-                "Large scraping job with errors. Consider implementing retry logic.".to_string(),
+                "Large scraping job with errors. Consider lowering max_concurrent_tasks or raising task_delay_ms, then re-run to retry the failed chapters.".to_string(),
             );
         }
 
@@ -178,5 +403,237 @@ impl ScrapingStats {
     }
 }
 
+/// How long a single scrape attempt for a chapter took
+#[derive(Debug, Clone)]
+pub struct ChapterTiming {
+    pub chapter_number: String,
+    pub url: String,
+    pub duration: Duration,
+    pub succeeded: bool,
+}
+
+impl std::fmt::Display for ChapterTiming {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Chapter {}: {:.1?} - {}",
+            self.chapter_number, self.duration, self.url
+        )?;
+        if !self.succeeded {
+            write!(f, " (failed)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Bounded record of the slowest and fastest scrape attempts of a run
+#[derive(Debug, Default, Clone)]
+pub struct ChapterTimings {
+    // Both kept sorted, slowest first and fastest first respectively
+    slowest: Vec<ChapterTiming>,
+    fastest: Vec<ChapterTiming>,
+}
+
+impl ChapterTimings {
+    pub fn record(&mut self, timing: ChapterTiming) {
+        Self::insert_bounded(&mut self.slowest, timing.clone(), |a, b| a > b);
+        Self::insert_bounded(&mut self.fastest, timing, |a, b| a < b);
+    }
+
+    /// Insert a timing into a list ordered by `before`, keeping at most `TIMING_EXTREMES` entries
+    fn insert_bounded(
+        list: &mut Vec<ChapterTiming>,
+        timing: ChapterTiming,
+        before: impl Fn(Duration, Duration) -> bool,
+    ) {
+        let position = list
+            .iter()
+            .position(|existing| before(timing.duration, existing.duration))
+            .unwrap_or(list.len());
+
+        if position < TIMING_EXTREMES {
+            list.insert(position, timing);
+            list.truncate(TIMING_EXTREMES);
+        }
+    }
+
+    pub fn slowest(&self) -> &[ChapterTiming] {
+        &self.slowest
+    }
+
+    pub fn fastest(&self) -> &[ChapterTiming] {
+        &self.fastest
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slowest.is_empty()
+    }
+}
+
 // Re-export the config type for convenience
-pub use crate::config::ScrapingConfig as Config;
+pub use crate::config::{
+    Auth, EmptyContentPolicy, ExistingPolicy, OutputLayout, ScrapingConfig as Config, SelectorSpec,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timing(chapter: usize, millis: u64) -> ChapterTiming {
+        ChapterTiming {
+            chapter_number: chapter.to_string(),
+            url: format!("https://example.com/{chapter}"),
+            duration: Duration::from_millis(millis),
+            succeeded: true,
+        }
+    }
+
+    #[test]
+    fn test_success_rate_collapse_needs_min_samples() {
+        let mut stats = ScrapingStats::default();
+        let record = ChapterRecord::new("https://example.com/1".to_string(), "1".to_string());
+
+        // Sporadic errors keep the moving average up
+        for i in 0..30 {
+            stats.record_attempt(&record, Duration::ZERO, i % 5 != 0);
+        }
+        assert_eq!(stats.collapsed_success_rate(50.0, 20), None);
+
+        // A run of failures drags it down, but only counts after enough samples
+        for _ in 0..10 {
+            stats.record_attempt(&record, Duration::ZERO, false);
+        }
+        assert!(stats.collapsed_success_rate(50.0, 20).is_some());
+        assert_eq!(stats.collapsed_success_rate(50.0, 100), None);
+    }
+
+    #[test]
+    fn test_errors_by_kind_drive_recommendations() {
+        let mut stats = ScrapingStats {
+            success_count: 10,
+            ..ScrapingStats::default()
+        };
+        stats.increment_permanent_error(ErrorKind::ContentExtraction);
+        stats.increment_permanent_error(ErrorKind::ContentExtraction);
+        stats.increment_permanent_error(ErrorKind::SelectorTimeout);
+        stats.increment_recoverable_error(ErrorKind::Http);
+
+        assert_eq!(stats.extraction_errors(), 3);
+        assert_eq!(stats.network_errors(), 1);
+        assert_eq!(
+            stats.errors_by_kind.values().sum::<usize>(),
+            stats.error_count
+        );
+        assert!(stats.summary_report().contains("└── Content extraction: 2"));
+        assert!(
+            stats
+                .get_recommendations()
+                .iter()
+                .any(|r| r.contains("content extraction errors"))
+        );
+    }
+
+    #[test]
+    fn test_large_failing_job_points_at_concurrency_settings() {
+        let stats = ScrapingStats {
+            total: 2000,
+            success_count: 1500,
+            error_count: 500,
+            ..ScrapingStats::default()
+        };
+
+        assert!(
+            stats
+                .get_recommendations()
+                .iter()
+                .any(|r| r.contains("max_concurrent_tasks") && r.contains("task_delay_ms"))
+        );
+    }
+
+    #[test]
+    fn test_refresh_change_counts_differing_lines() {
+        let previous = "Line one\nLine two\nLine three\n";
+
+        assert_eq!(
+            RefreshChange::between(previous.as_bytes(), previous),
+            RefreshChange::Unchanged
+        );
+        for same_lines in [
+            "Line two\nLine one\nLine three",
+            "Line one\r\nLine two\r\nLine three\r\n",
+        ] {
+            let change = RefreshChange::between(previous.as_bytes(), same_lines);
+            assert_eq!(
+                change,
+                RefreshChange::Changed {
+                    added: 0,
+                    removed: 0
+                }
+            );
+            assert_eq!(
+                change.to_string(),
+                "lines reordered or line endings changed"
+            );
+        }
+        assert_eq!(
+            RefreshChange::between(
+                previous.as_bytes(),
+                "Line one\nLine 2\nLine three\nAuthor's note\n"
+            ),
+            RefreshChange::Changed {
+                added: 2,
+                removed: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_runtime_is_accumulated_per_host() {
+        let mut stats = ScrapingStats::default();
+        let first = ChapterRecord::new("https://Example.com/1".to_string(), "1".to_string());
+        let second = ChapterRecord::new("https://example.com/2".to_string(), "2".to_string());
+        let other = ChapterRecord::new("https://other.org/1".to_string(), "3".to_string());
+
+        stats.record_attempt(&first, Duration::from_secs(2), true);
+        stats.record_attempt(&second, Duration::from_secs(3), false);
+        stats.record_attempt(&other, Duration::from_secs(1), true);
+
+        assert_eq!(
+            stats.runtime_for_host_of("https://example.com/9"),
+            Duration::from_secs(5)
+        );
+        assert_eq!(
+            stats.runtime_for_host_of("https://other.org/"),
+            Duration::from_secs(1)
+        );
+        assert_eq!(
+            stats.runtime_for_host_of("https://new.net/"),
+            Duration::ZERO
+        );
+    }
+
+    #[test]
+    fn test_timings_keep_bounded_extremes() {
+        let mut timings = ChapterTimings::default();
+        for (chapter, millis) in [300, 50, 900, 10, 700, 400, 20, 800]
+            .into_iter()
+            .enumerate()
+        {
+            timings.record(timing(chapter, millis));
+        }
+
+        let slowest: Vec<u64> = timings
+            .slowest()
+            .iter()
+            .map(|t| t.duration.as_millis() as u64)
+            .collect();
+        let fastest: Vec<u64> = timings
+            .fastest()
+            .iter()
+            .map(|t| t.duration.as_millis() as u64)
+            .collect();
+
+        assert_eq!(slowest, vec![900, 800, 700, 400, 300]);
+        assert_eq!(fastest, vec![10, 20, 50, 300, 400]);
+    }
+}
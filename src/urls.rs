@@ -0,0 +1,236 @@
+use url::Url;
+
+/// Schemes accepted in the input CSV unless configured otherwise
+pub const DEFAULT_ALLOWED_SCHEMES: [&str; 2] = ["http", "https"];
+
+/// Schemes the scraper knows how to fetch
+pub const SUPPORTED_SCHEMES: [&str; 3] = ["http", "https", "file"];
+
+/// Placeholder replaced by the chapter number in `fallback_url_template`
+pub const FALLBACK_CHAPTER_PLACEHOLDER: &str = "{chapter}";
+
+/// Fill a `fallback_url_template` for a chapter
+pub fn fallback_url(template: &str, chapter_number: &str) -> String {
+    template.replace(FALLBACK_CHAPTER_PLACEHOLDER, chapter_number)
+}
+
+/// Query parameters dropped by `canonicalize_urls` unless configured otherwise. A trailing `*`
+/// matches any parameter starting with the rest.
+pub const DEFAULT_TRACKING_PARAMS: [&str; 6] =
+    ["utm_*", "fbclid", "gclid", "mc_cid", "mc_eid", "ref"];
+
+fn param_matches(pattern: &str, name: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => name.starts_with(prefix),
+        None => name == pattern,
+    }
+}
+
+/// A URL without the query parameters matching `params`, so links that only differ by
+/// tracking parameters compare equal. URLs that don't parse are returned unchanged.
+pub fn canonical_url(url: &str, params: &[String]) -> String {
+    let Ok(mut parsed) = Url::parse(url) else {
+        return url.to_string();
+    };
+
+    let pairs: Vec<(String, String)> = parsed.query_pairs().into_owned().collect();
+    let kept: Vec<&(String, String)> = pairs
+        .iter()
+        .filter(|(name, _)| !params.iter().any(|pattern| param_matches(pattern, name)))
+        .collect();
+
+    // Leave the query as written unless something has to go
+    if kept.len() < pairs.len() {
+        if kept.is_empty() {
+            parsed.set_query(None);
+        } else {
+            parsed.query_pairs_mut().clear().extend_pairs(kept);
+        }
+    }
+
+    parsed.to_string()
+}
+
+/// Parse a chapter URL and check that its scheme is one of `allowed_schemes`
+pub fn validate_url(url: &str, allowed_schemes: &[String]) -> Result<Url, String> {
+    let parsed = Url::parse(url).map_err(|e| format!("Invalid URL format: '{url}' ({e})"))?;
+
+    if !allowed_schemes
+        .iter()
+        .any(|scheme| scheme.eq_ignore_ascii_case(parsed.scheme()))
+    {
+        return Err(format!(
+            "Invalid URL format: '{url}'. URLs must use one of these schemes: {}",
+            allowed_schemes.join(", ")
+        ));
+    }
+
+    Ok(parsed)
+}
+
+/// Host name of a URL, if it parses and has one
+pub fn host_of(url: &str) -> Option<String> {
+    Url::parse(url)
+        .ok()
+        .and_then(|u| u.host_str().map(str::to_string))
+}
+
+/// Check whether a host matches a pattern such as `example.com` or `*.example.com`.
+/// Wildcard patterns match subdomains only, not the bare domain.
+pub fn host_matches(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.trim().to_ascii_lowercase();
+    let host = host.to_ascii_lowercase();
+
+    match pattern.strip_prefix("*.") {
+        Some(domain) => host
+            .strip_suffix(domain)
+            .is_some_and(|prefix| prefix.len() > 1 && prefix.ends_with('.')),
+        None => host == pattern,
+    }
+}
+
+/// Validate a host pattern from the configuration
+pub fn validate_host_pattern(pattern: &str) -> Result<(), String> {
+    let domain = pattern.strip_prefix("*.").unwrap_or(pattern);
+    if domain.is_empty() || domain.contains(['*', '/', ':', ' ']) {
+        return Err(format!(
+            "invalid host pattern '{pattern}'; use a host name like 'example.com' or '*.example.com'"
+        ));
+    }
+    Ok(())
+}
+
+/// Allow/deny lists of hosts that chapter URLs are checked against before any request
+#[derive(Debug, Clone, Default)]
+pub struct HostFilter {
+    allowed: Vec<String>,
+    blocked: Vec<String>,
+}
+
+impl HostFilter {
+    pub fn new(allowed: &[String], blocked: &[String]) -> Self {
+        Self {
+            allowed: allowed.to_vec(),
+            blocked: blocked.to_vec(),
+        }
+    }
+
+    /// Returns the reason a URL is filtered out, or `None` if it may be scraped
+    pub fn rejection_reason(&self, url: &str) -> Option<String> {
+        if self.allowed.is_empty() && self.blocked.is_empty() {
+            return None;
+        }
+
+        let host = host_of(url).unwrap_or_default();
+
+        if let Some(pattern) = self.blocked.iter().find(|p| host_matches(p, &host)) {
+            return Some(format!("host '{host}' is blocked by '{pattern}'"));
+        }
+
+        if !self.allowed.is_empty() && !self.allowed.iter().any(|p| host_matches(p, &host)) {
+            return Some(format!("host '{host}' is not in allowed_hosts"));
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schemes(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_canonical_url_strips_tracking_params() {
+        let params = schemes(&DEFAULT_TRACKING_PARAMS);
+
+        assert_eq!(
+            canonical_url(
+                "https://example.com/chapter-1?utm_source=feed&utm_medium=rss",
+                &params
+            ),
+            "https://example.com/chapter-1"
+        );
+        assert_eq!(
+            canonical_url("https://example.com/read?id=5&fbclid=abc&page=2", &params),
+            "https://example.com/read?id=5&page=2"
+        );
+        assert_eq!(
+            canonical_url("https://EXAMPLE.com/read?id=5", &params),
+            "https://example.com/read?id=5"
+        );
+        assert_eq!(canonical_url("not a url", &params), "not a url");
+    }
+
+    #[test]
+    fn test_default_schemes() {
+        let allowed = schemes(&DEFAULT_ALLOWED_SCHEMES);
+        assert!(validate_url("http://example.com/chapter/1", &allowed).is_ok());
+        assert!(validate_url("https://example.com/chapter/1", &allowed).is_ok());
+        assert!(validate_url("file:///tmp/chapter_1.html", &allowed).is_err());
+        assert!(validate_url("ftp://example.com/chapter/1", &allowed).is_err());
+    }
+
+    #[test]
+    fn test_file_scheme_when_allowed() {
+        let allowed = schemes(&["https", "file"]);
+        assert!(validate_url("file:///tmp/chapter_1.html", &allowed).is_ok());
+        assert!(validate_url("https://example.com/chapter/1", &allowed).is_ok());
+        assert!(validate_url("http://example.com/chapter/1", &allowed).is_err());
+    }
+
+    #[test]
+    fn test_scheme_match_is_case_insensitive() {
+        let allowed = schemes(&["HTTPS"]);
+        assert!(validate_url("https://example.com/chapter/1", &allowed).is_ok());
+    }
+
+    #[test]
+    fn test_malformed_urls_rejected() {
+        let allowed = schemes(&DEFAULT_ALLOWED_SCHEMES);
+        assert!(validate_url("", &allowed).is_err());
+        assert!(validate_url("example.com/chapter/1", &allowed).is_err());
+        assert!(validate_url("/chapter/1", &allowed).is_err());
+    }
+
+    #[test]
+    fn test_host_patterns() {
+        assert!(host_matches("example.com", "example.com"));
+        assert!(host_matches("example.com", "EXAMPLE.com"));
+        assert!(!host_matches("example.com", "www.example.com"));
+        assert!(host_matches("*.example.com", "www.example.com"));
+        assert!(host_matches("*.example.com", "a.b.example.com"));
+        assert!(!host_matches("*.example.com", "example.com"));
+        assert!(!host_matches("*.example.com", "badexample.com"));
+    }
+
+    #[test]
+    fn test_host_filter() {
+        let filter = HostFilter::new(
+            &schemes(&["*.example.com", "example.org"]),
+            &schemes(&["ads.example.com"]),
+        );
+        assert!(
+            filter
+                .rejection_reason("https://www.example.com/1")
+                .is_none()
+        );
+        assert!(filter.rejection_reason("https://example.org/1").is_none());
+        assert!(
+            filter
+                .rejection_reason("https://ads.example.com/1")
+                .is_some()
+        );
+        assert!(filter.rejection_reason("https://other.net/1").is_some());
+
+        let unrestricted = HostFilter::default();
+        assert!(
+            unrestricted
+                .rejection_reason("https://other.net/1")
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,180 @@
+//! `--verify`: checks the output of earlier runs against the CSV without fetching anything,
+//! listing chapters that are missing or look broken so they can be scraped again.
+
+use crate::csv_reader::{self, CsvReader};
+use crate::error::{ScrapperError, ScrapperResult};
+use crate::file_manager::FileManager;
+use crate::types::{ChapterRecord, Config};
+use crate::web_scraper::MIN_CONTENT_LEN;
+use tokio::fs;
+
+/// Error page markers are only looked for in files this small, so chapters quoting them in
+/// their text aren't flagged
+const ERROR_PAGE_MAX_LEN: usize = 2048;
+
+/// Why a chapter's output needs to be scraped again
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerifyProblem {
+    Missing,
+    TooSmall { bytes: usize },
+    ErrorPage { marker: String },
+    Unreadable(String),
+}
+
+impl std::fmt::Display for VerifyProblem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Missing => write!(f, "missing or empty"),
+            Self::TooSmall { bytes } => {
+                write!(f, "only {bytes} bytes (minimum {MIN_CONTENT_LEN})")
+            }
+            Self::ErrorPage { marker } => write!(f, "looks like an error page ('{marker}')"),
+            Self::Unreadable(message) => write!(f, "can't be read: {message}"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct VerifyIssue {
+    pub record: ChapterRecord,
+    pub problem: VerifyProblem,
+}
+
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    pub checked: usize,
+    pub issues: Vec<VerifyIssue>,
+}
+
+/// Check a chapter's saved text with the same minimum as extraction, and for error page markers
+fn check_content(content: &str, error_page_markers: &[String]) -> Option<VerifyProblem> {
+    if content.trim().len() < MIN_CONTENT_LEN {
+        return Some(VerifyProblem::TooSmall {
+            bytes: content.trim().len(),
+        });
+    }
+
+    if content.len() <= ERROR_PAGE_MAX_LEN {
+        let lowercase = content.to_lowercase();
+        if let Some(marker) = error_page_markers
+            .iter()
+            .find(|marker| lowercase.contains(&marker.to_lowercase()))
+        {
+            return Some(VerifyProblem::ErrorPage {
+                marker: marker.clone(),
+            });
+        }
+    }
+
+    None
+}
+
+/// Check the output file of every record
+pub async fn verify_outputs(
+    records: &[ChapterRecord],
+    file_manager: &FileManager,
+    error_page_markers: &[String],
+) -> VerifyReport {
+    let mut report = VerifyReport::default();
+
+    for record in records {
+        report.checked += 1;
+
+        let problem = if !file_manager.chapter_exists(record) {
+            Some(VerifyProblem::Missing)
+        } else {
+            match fs::read_to_string(file_manager.get_chapter_path(record)).await {
+                Ok(content) => check_content(&content, error_page_markers),
+                Err(e) => Some(VerifyProblem::Unreadable(e.to_string())),
+            }
+        };
+
+        if let Some(problem) = problem {
+            report.issues.push(VerifyIssue {
+                record: record.clone(),
+                problem,
+            });
+        }
+    }
+
+    report
+}
+
+/// Run `--verify`: report chapters needing another scrape and write them to a CSV usable as
+/// input. Returns whether every chapter passed.
+pub async fn run(config: &Config) -> ScrapperResult<bool> {
+    if config.sqlite_database.is_some() {
+        return Err(ScrapperError::config(
+            "--verify checks chapter files, which aren't written with sqlite_database",
+        ));
+    }
+
+    let records = CsvReader::new(config).read_records().await?;
+    let file_manager = FileManager::new(config);
+
+    println!(
+        "🔍 Verifying {} chapters in {:?}...",
+        records.len(),
+        config.output_dir
+    );
+    let report = verify_outputs(&records, &file_manager, &config.error_page_markers).await;
+
+    if report.issues.is_empty() {
+        println!("✅ All {} chapters look complete", report.checked);
+        return Ok(true);
+    }
+
+    for issue in &report.issues {
+        println!(
+            "   ❌ Chapter {}: {} - {}",
+            issue.record.chapter_number, issue.problem, issue.record.url
+        );
+    }
+
+    let failures: Vec<ChapterRecord> = report
+        .issues
+        .iter()
+        .map(|issue| issue.record.clone())
+        .collect();
+    let path = file_manager.verify_failures_path();
+    csv_reader::write_records(&path, &failures).await?;
+
+    println!(
+        "⚠️  {} of {} chapters need to be scraped again; written to {path:?}",
+        report.issues.len(),
+        report.checked
+    );
+    println!("💡 Remove the broken files and run with --input {path:?} to redo them");
+
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn markers() -> Vec<String> {
+        vec!["Access Denied".to_string(), "Just a moment".to_string()]
+    }
+
+    #[test]
+    fn test_check_content_flags_short_and_error_pages() {
+        assert_eq!(
+            check_content("  tiny  ", &markers()),
+            Some(VerifyProblem::TooSmall { bytes: 4 })
+        );
+
+        let blocked = format!("ACCESS DENIED. {}", "You don't have permission. ".repeat(5));
+        assert_eq!(
+            check_content(&blocked, &markers()),
+            Some(VerifyProblem::ErrorPage {
+                marker: "Access Denied".to_string()
+            })
+        );
+
+        // Long chapters may quote a marker in their text
+        let chapter = format!("{} Access denied, she said.", "Story text. ".repeat(200));
+        assert_eq!(check_content(&chapter, &markers()), None);
+        assert_eq!(check_content(&blocked, &[]), None);
+    }
+}
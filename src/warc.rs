@@ -0,0 +1,247 @@
+//! Minimal WARC 1.1 writer: archives each fetched page as a request/response record pair.
+
+use crate::error::{ScrapperError, ScrapperResult};
+use reqwest::header::HeaderMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+const WARC_VERSION: &str = "WARC/1.1";
+
+/// One HTTP request/response pair to archive
+pub struct HttpExchange<'a> {
+    pub url: &'a str,
+    pub user_agent: &'a str,
+    pub accept_language: Option<&'a str>,
+    /// Status line such as `HTTP/1.1 200 OK`
+    pub status_line: String,
+    pub response_headers: &'a HeaderMap,
+    pub body: &'a [u8],
+}
+
+/// Appends WARC records to a single file shared by all scraping tasks
+pub struct WarcWriter {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl WarcWriter {
+    /// Open a WARC file for appending, writing a `warcinfo` record if it is new
+    pub fn open(path: &Path) -> ScrapperResult<Self> {
+        let file_error = |e: std::io::Error| {
+            ScrapperError::file_system(
+                format!("Failed to open WARC file: {e}"),
+                Some(path.to_path_buf()),
+            )
+        };
+
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent).map_err(file_error)?;
+        }
+
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(file_error)?;
+
+        if file.metadata().map_err(file_error)?.len() == 0 {
+            file.write_all(&warcinfo_record()).map_err(file_error)?;
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: Mutex::new(File::from_std(file)),
+        })
+    }
+
+    /// Append the request and response records of one fetch, kept adjacent in the file
+    pub async fn write_exchange(&self, exchange: &HttpExchange<'_>) -> ScrapperResult<()> {
+        let request_id = record_id();
+        let response_id = record_id();
+
+        let mut records = record(
+            "request",
+            &request_id,
+            exchange.url,
+            "application/http;msgtype=request",
+            &[("WARC-Concurrent-To", response_id.as_str())],
+            &request_block(exchange),
+        );
+        records.extend(record(
+            "response",
+            &response_id,
+            exchange.url,
+            "application/http;msgtype=response",
+            &[],
+            &response_block(exchange),
+        ));
+
+        let mut file = self.file.lock().await;
+        file.write_all(&records).await.map_err(|e| {
+            ScrapperError::file_system(
+                format!("Failed to write WARC record: {e}"),
+                Some(self.path.clone()),
+            )
+        })?;
+        file.flush().await.map_err(|e| {
+            ScrapperError::file_system(
+                format!("Failed to flush WARC file: {e}"),
+                Some(self.path.clone()),
+            )
+        })
+    }
+}
+
+fn record_id() -> String {
+    format!("<urn:uuid:{}>", Uuid::new_v4())
+}
+
+fn warc_date() -> String {
+    chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+fn warcinfo_record() -> Vec<u8> {
+    let fields = format!(
+        "software: scrapper/{}\r\nformat: WARC File Format 1.1\r\n",
+        env!("CARGO_PKG_VERSION")
+    );
+    let mut info = format!(
+        "{WARC_VERSION}\r\nWARC-Type: warcinfo\r\nWARC-Record-ID: {}\r\nWARC-Date: {}\r\n",
+        record_id(),
+        warc_date()
+    );
+    info.push_str(&format!(
+        "Content-Type: application/warc-fields\r\nContent-Length: {}\r\n\r\n{fields}\r\n\r\n",
+        fields.len()
+    ));
+    info.into_bytes()
+}
+
+/// Serialize a single WARC record around an HTTP message block
+fn record(
+    warc_type: &str,
+    id: &str,
+    target_uri: &str,
+    content_type: &str,
+    extra_headers: &[(&str, &str)],
+    block: &[u8],
+) -> Vec<u8> {
+    let mut header = format!(
+        "{WARC_VERSION}\r\nWARC-Type: {warc_type}\r\nWARC-Record-ID: {id}\r\nWARC-Date: {}\r\nWARC-Target-URI: {target_uri}\r\n",
+        warc_date()
+    );
+    for (name, value) in extra_headers {
+        header.push_str(&format!("{name}: {value}\r\n"));
+    }
+    header.push_str(&format!(
+        "Content-Type: {content_type}\r\nContent-Length: {}\r\n\r\n",
+        block.len()
+    ));
+
+    let mut bytes = header.into_bytes();
+    bytes.extend_from_slice(block);
+    bytes.extend_from_slice(b"\r\n\r\n");
+    bytes
+}
+
+/// Reconstruct the GET request sent for a page
+fn request_block(exchange: &HttpExchange<'_>) -> Vec<u8> {
+    let parsed = url::Url::parse(exchange.url).ok();
+    let target = parsed
+        .as_ref()
+        .map(|u| match u.query() {
+            Some(query) => format!("{}?{query}", u.path()),
+            None => u.path().to_string(),
+        })
+        .unwrap_or_else(|| "/".to_string());
+    let host = parsed
+        .as_ref()
+        .and_then(|u| {
+            u.host_str().map(|host| match u.port() {
+                Some(port) => format!("{host}:{port}"),
+                None => host.to_string(),
+            })
+        })
+        .unwrap_or_default();
+
+    let mut request = format!(
+        "GET {target} HTTP/1.1\r\nHost: {host}\r\nUser-Agent: {}\r\nAccept: */*\r\n",
+        exchange.user_agent
+    );
+    if let Some(accept_language) = exchange.accept_language {
+        request.push_str(&format!("Accept-Language: {accept_language}\r\n"));
+    }
+    request.push_str("\r\n");
+    request.into_bytes()
+}
+
+/// Status line, headers and body of a response. The body has already been de-chunked,
+/// so any `Transfer-Encoding` header is dropped to keep the message consistent.
+fn response_block(exchange: &HttpExchange<'_>) -> Vec<u8> {
+    let mut head = format!("{}\r\n", exchange.status_line);
+    for (name, value) in exchange.response_headers {
+        if name == reqwest::header::TRANSFER_ENCODING {
+            continue;
+        }
+        head.push_str(&format!(
+            "{name}: {}\r\n",
+            String::from_utf8_lossy(value.as_bytes())
+        ));
+    }
+    head.push_str("\r\n");
+
+    let mut bytes = head.into_bytes();
+    bytes.extend_from_slice(exchange.body);
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::{CONTENT_TYPE, HeaderValue, TRANSFER_ENCODING};
+
+    #[test]
+    fn test_exchange_records() {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_TYPE, HeaderValue::from_static("text/html"));
+        headers.insert(TRANSFER_ENCODING, HeaderValue::from_static("chunked"));
+        let exchange = HttpExchange {
+            url: "https://example.com:8443/novel/1?lang=en",
+            user_agent: "scrapper-test",
+            accept_language: Some("fr-FR, fr;q=0.9"),
+            status_line: "HTTP/1.1 200 OK".to_string(),
+            response_headers: &headers,
+            body: b"<html></html>",
+        };
+
+        let request = String::from_utf8(request_block(&exchange)).unwrap();
+        assert!(request.starts_with("GET /novel/1?lang=en HTTP/1.1\r\nHost: example.com:8443\r\n"));
+        assert!(request.ends_with("Accept-Language: fr-FR, fr;q=0.9\r\n\r\n"));
+
+        let response = String::from_utf8(response_block(&exchange)).unwrap();
+        assert_eq!(
+            response,
+            "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\n\r\n<html></html>"
+        );
+
+        let block = response_block(&exchange);
+        let warc = String::from_utf8(record(
+            "response",
+            "<urn:uuid:test>",
+            exchange.url,
+            "application/http;msgtype=response",
+            &[],
+            &block,
+        ))
+        .unwrap();
+        assert!(warc.starts_with("WARC/1.1\r\nWARC-Type: response\r\n"));
+        assert!(warc.contains(&format!("Content-Length: {}\r\n\r\n", block.len())));
+        assert!(warc.ends_with("<html></html>\r\n\r\n"));
+    }
+}
@@ -1,36 +1,210 @@
+use crate::cookies;
 use crate::error::{ScrapperError, ScrapperResult};
-use crate::types::{ChapterRecord, Config};
-use indicatif::ProgressBar;
-use scraper::{Html, Selector};
+use crate::file_manager::{BUNDLE_ASSETS_DIR, FileManager, bounded_file_name};
+use crate::progress::ChapterLog;
+#[cfg(feature = "render")]
+use crate::render::PageRenderer;
+#[cfg(feature = "sqlite")]
+use crate::sqlite_sink::{ChapterRow, SqliteSink};
+use crate::types::{
+    Auth, ChapterRecord, Config, EmptyContentPolicy, ExistingPolicy, OutputLayout, RefreshChange,
+};
+use crate::urls::{self, HostFilter};
+use crate::warc::{HttpExchange, WarcWriter};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use encoding_rs::{Encoding, UTF_8};
+use reqwest::header::{
+    ACCEPT_LANGUAGE, AUTHORIZATION, CONTENT_TYPE, HeaderMap, HeaderName, HeaderValue, LOCATION,
+};
+use scraper::{Html, Node, Selector};
+use std::collections::HashSet;
 use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Duration;
-use tokio::fs::File;
+use tokio::fs::{self, File};
 use tokio::io::AsyncWriteExt;
+use tokio::sync::{Semaphore, SemaphorePermit};
+use url::Url;
 
-pub struct ContentExtractor {
+/// Minimum length of extracted content; anything shorter is treated as a parsing failure
+pub const MIN_CONTENT_LEN: usize = 100;
+
+/// Share of U+FFFD replacement characters in extracted text above which `detect_encoding_errors`
+/// rejects it. A few stray ones come from broken bytes in otherwise fine pages.
+const MAX_REPLACEMENT_CHAR_RATIO: f64 = 0.01;
+
+/// Text extracted from a page, plus any images referenced from it
+#[derive(Debug, Default)]
+pub struct ExtractedContent {
+    pub text: String,
+    pub images: Vec<ImageAsset>,
+    /// The page's `<title>`, if it has a non-empty one
+    pub title: Option<String>,
+}
+
+/// An image to download into a chapter bundle's assets folder
+#[derive(Debug, Clone)]
+pub struct ImageAsset {
+    pub url: String,
+    pub file_name: String,
+}
+
+/// One way of finding a chapter's text: `selector` with `skip_text_nodes`, or an entry of
+/// `selectors`
+struct Candidate {
+    /// As configured, for error messages
     selector: String,
+    // Each comma-separated selector, compiled once and tried in order
+    selectors: Vec<Selector>,
     skip_nodes: usize,
+    // Elements inside the content element whose text is left out
+    exclude: Vec<Selector>,
+}
+
+impl Candidate {
+    /// `field` is the config field the selectors come from, for validation errors
+    fn new(
+        field: &str,
+        selector: &str,
+        skip_nodes: usize,
+        exclude: &[String],
+    ) -> ScrapperResult<Self> {
+        let parse = |selector: &str| {
+            Selector::parse(selector).map_err(|e| {
+                ScrapperError::validation(
+                    field,
+                    format!("Invalid CSS selector '{selector}': {e:?}"),
+                )
+            })
+        };
+
+        // Validate selector by attempting to parse it
+        parse(selector)?;
+
+        // Pieces that don't parse on their own are skipped, as they never match
+        let selectors = selector
+            .split(',')
+            .filter_map(|s| Selector::parse(s.trim()).ok())
+            .collect();
+
+        Ok(Self {
+            selector: selector.to_string(),
+            selectors,
+            skip_nodes,
+            exclude: exclude
+                .iter()
+                .map(|selector| parse(selector))
+                .collect::<ScrapperResult<_>>()?,
+        })
+    }
+}
+
+pub struct ContentExtractor {
+    // Tried in order until one yields valid content
+    candidates: Vec<Candidate>,
+    title_selector: Selector,
     filter_patterns: Vec<String>,
+    // Text nodes removed by each of `filter_patterns` so far, shared by all tasks
+    filter_hits: Vec<AtomicUsize>,
+    collect_images: bool,
+    extract_from_comments: bool,
+    include_noscript: bool,
+    empty_content_policy: EmptyContentPolicy,
+    detect_encoding_errors: bool,
+    trim_leading_lines: usize,
+    trim_trailing_lines: usize,
 }
 
 impl ContentExtractor {
     pub fn new(config: &Config) -> ScrapperResult<Self> {
-        // Validate selector by attempting to parse it
-        let _ = Selector::parse(&config.selector).map_err(|e| {
-            ScrapperError::validation(
+        let candidates = if config.selectors.is_empty() {
+            vec![Candidate::new(
                 "selector",
-                format!("Invalid CSS selector '{}': {:?}", config.selector, e),
-            )
-        })?;
+                &config.selector,
+                config.skip_text_nodes,
+                &[],
+            )?]
+        } else {
+            config
+                .selectors
+                .iter()
+                .map(|spec| {
+                    Candidate::new(
+                        "selectors",
+                        &spec.selector,
+                        spec.skip_text_nodes.unwrap_or(config.skip_text_nodes),
+                        &spec.exclude,
+                    )
+                })
+                .collect::<ScrapperResult<_>>()?
+        };
 
         Ok(Self {
-            selector: config.selector.clone(),
-            skip_nodes: config.skip_text_nodes,
+            candidates,
+            title_selector: Selector::parse("title").expect("static selector is valid"),
             filter_patterns: config.filter_patterns.clone(),
+            filter_hits: config
+                .filter_patterns
+                .iter()
+                .map(|_| AtomicUsize::new(0))
+                .collect(),
+            collect_images: config.output_layout == OutputLayout::Bundle,
+            extract_from_comments: config.extract_from_comments,
+            include_noscript: config.include_noscript,
+            empty_content_policy: config.empty_content_policy,
+            detect_encoding_errors: config.detect_encoding_errors,
+            trim_leading_lines: config.trim_leading_lines,
+            trim_trailing_lines: config.trim_trailing_lines,
         })
     }
 
-    pub fn extract_content(&self, html: &str, url: &str) -> ScrapperResult<String> {
+    pub fn extract_content(&self, html: &str, url: &str) -> ScrapperResult<ExtractedContent> {
+        self.extract_content_with_partial(html, url, &mut None)
+    }
+
+    /// `extract_content` on tokio's blocking thread pool. Parsing is CPU-bound, and a large page
+    /// parsed inline holds up every other task scheduled on the same worker thread.
+    pub async fn extract_content_async(
+        self: &Arc<Self>,
+        html: Arc<str>,
+        url: &str,
+    ) -> ScrapperResult<ExtractedContent> {
+        self.extract_content_async_with_partial(html, url, &mut None)
+            .await
+    }
+
+    /// `extract_content_with_partial` on tokio's blocking thread pool
+    pub async fn extract_content_async_with_partial(
+        self: &Arc<Self>,
+        html: Arc<str>,
+        url: &str,
+        partial: &mut Option<String>,
+    ) -> ScrapperResult<ExtractedContent> {
+        let extractor = Arc::clone(self);
+        let task_url = url.to_string();
+        let (result, rejected) = tokio::task::spawn_blocking(move || {
+            let mut rejected = None;
+            let result = extractor.extract_content_with_partial(&html, &task_url, &mut rejected);
+            (result, rejected)
+        })
+        .await
+        .map_err(|e| {
+            ScrapperError::task_execution(format!("Content extraction task failed: {e}"))
+        })?;
+
+        *partial = rejected;
+        result
+    }
+
+    /// Like `extract_content`, also handing back the text of a page rejected as too short
+    pub fn extract_content_with_partial(
+        &self,
+        html: &str,
+        url: &str,
+        partial: &mut Option<String>,
+    ) -> ScrapperResult<ExtractedContent> {
         if html.is_empty() {
             return Err(ScrapperError::content_extraction(
                 url,
@@ -40,139 +214,818 @@ impl ContentExtractor {
 
         let document = Html::parse_document(html);
 
-        // Try each selector in the list (separated by commas)
-        let selectors: Vec<&str> = self.selector.split(',').map(|s| s.trim()).collect();
-        let mut element = None;
-
-        for selector_str in selectors {
-            if let Ok(selector) = Selector::parse(selector_str) {
-                if let Some(found_element) = document.select(&selector).next() {
-                    element = Some(found_element);
-                    break;
-                }
+        let mut failures = Vec::new();
+        let mut empty = None;
+        for candidate in &self.candidates {
+            let mut candidate_partial = None;
+            match self.extract_with(candidate, &document, url, &mut candidate_partial) {
+                // Only with `empty_content_policy = "skip"`, once no other candidate has content
+                Ok(extracted) if extracted.text.is_empty() => empty = Some(extracted),
+                Ok(extracted) => return Ok(extracted),
+                Err(e) => failures.push((candidate, e)),
+            }
+            if partial.is_none() {
+                *partial = candidate_partial;
             }
         }
 
+        if let Some(empty) = empty {
+            return Ok(empty);
+        }
+        if failures.len() == 1 {
+            return Err(failures.remove(0).1);
+        }
+
+        let reasons: Vec<String> = failures
+            .iter()
+            .map(|(candidate, error)| {
+                let reason = match error {
+                    ScrapperError::ContentExtraction { message, .. } => message.clone(),
+                    other => other.to_string(),
+                };
+                format!("'{}': {reason}", candidate.selector)
+            })
+            .collect();
+        Err(ScrapperError::content_extraction(
+            url,
+            format!("No selector yielded valid content. {}", reasons.join(" ")),
+        ))
+    }
+
+    /// Extract a chapter's text with one candidate of `selectors`
+    fn extract_with(
+        &self,
+        candidate: &Candidate,
+        document: &Html,
+        url: &str,
+        partial: &mut Option<String>,
+    ) -> ScrapperResult<ExtractedContent> {
+        // Try each selector in the list (separated by commas)
+        let element = candidate
+            .selectors
+            .iter()
+            .find_map(|selector| document.select(selector).next());
+
         let element = element.ok_or_else(|| {
             ScrapperError::content_extraction(
                 url,
                 format!(
                     "No element found matching any of the selectors: {}",
-                    self.selector
+                    candidate.selector
                 ),
             )
         })?;
 
-        let mut content = String::new();
-        let text_nodes: Vec<_> = element.text().collect();
+        let excluded: HashSet<_> = candidate
+            .exclude
+            .iter()
+            .flat_map(|selector| element.select(selector))
+            .map(|excluded| excluded.id())
+            .collect();
 
-        if text_nodes.is_empty() {
-            return Err(ScrapperError::content_extraction(
-                url,
-                "No text content found in the selected element",
-            ));
-        }
+        let mut content = String::new();
+        let mut images = Vec::new();
+        let mut nodes = NodeCounts::default();
 
-        for (i, text_node) in text_nodes.iter().enumerate() {
-            // Skip initial text nodes as specified
-            if i < self.skip_nodes {
+        for node in element.descendants() {
+            if !excluded.is_empty()
+                && node
+                    .ancestors()
+                    .chain(std::iter::once(node))
+                    .any(|n| excluded.contains(&n.id()))
+            {
                 continue;
             }
 
-            let text = text_node.trim();
+            match node.value() {
+                Node::Text(text_node) => {
+                    // `<noscript>` content is kept as raw markup; parse it when asked to
+                    let in_noscript = node
+                        .parent()
+                        .and_then(|parent| parent.value().as_element().map(|e| e.name()))
+                        == Some("noscript");
 
-            // Skip empty text nodes
-            if text.is_empty() {
-                continue;
+                    if self.include_noscript && in_noscript {
+                        self.collect_fragment_text(text_node, candidate, &mut nodes, &mut content);
+                    } else {
+                        self.collect_text(text_node, candidate, &mut nodes, &mut content);
+                    }
+                }
+                Node::Comment(comment) if self.extract_from_comments => {
+                    self.collect_fragment_text(comment, candidate, &mut nodes, &mut content);
+                }
+                Node::Element(img) if self.collect_images && img.name() == "img" => {
+                    if let Some(asset) = img
+                        .attr("src")
+                        .and_then(|src| Self::image_asset(url, src, images.len() + 1))
+                    {
+                        // Reference the image relative to the chapter's index file
+                        content.push_str(&format!(
+                            "[image: {BUNDLE_ASSETS_DIR}/{}]\n",
+                            asset.file_name
+                        ));
+                        images.push(asset);
+                    }
+                }
+                _ => {}
             }
+        }
 
-            // Filter out unwanted content
-            if self.should_filter_text(text) {
-                continue;
-            }
+        if self.trim_leading_lines > 0 || self.trim_trailing_lines > 0 {
+            content = trim_lines(&content, self.trim_leading_lines, self.trim_trailing_lines);
+        }
 
-            content.push_str(text);
-            content.push('\n');
+        // An element with no text at all, or none left after filtering
+        let empty = nodes.total == 0 || content.trim().is_empty();
+        if empty && self.empty_content_policy == EmptyContentPolicy::Skip {
+            return Ok(ExtractedContent::default());
+        }
+
+        if nodes.total == 0 {
+            return Err(ScrapperError::content_extraction(
+                url,
+                "No text content found in the selected element",
+            ));
         }
 
         if content.trim().is_empty() {
             return Err(ScrapperError::content_extraction(
                 url,
                 format!(
-                    "No valid content found after filtering and processing. Skipped {} text nodes, applied {} filters.",
-                    self.skip_nodes,
-                    self.filter_patterns.len()
+                    "No valid content found after filtering and processing. {}, applied {} filters.{}",
+                    nodes.describe(),
+                    self.filter_patterns.len(),
+                    nodes.skip_hint()
                 ),
             ));
         }
 
         // Basic content quality check
-        if content.len() < 100 {
-            return Err(ScrapperError::content_extraction(
+        if content.len() < MIN_CONTENT_LEN {
+            let error = ScrapperError::content_extraction(
                 url,
                 format!(
-                    "Extracted content is too short ({} characters). This might indicate a parsing error.",
-                    content.len()
+                    "Extracted content is too short ({} characters). This might indicate a parsing error. {}.{}",
+                    content.len(),
+                    nodes.describe(),
+                    nodes.skip_hint()
                 ),
-            ));
+            );
+            *partial = Some(content);
+            return Err(error);
         }
 
-        Ok(content)
+        // Decoding never fails, it replaces what it can't read, so check how much it replaced
+        if self.detect_encoding_errors {
+            let total = content.chars().count();
+            let replaced = content
+                .chars()
+                .filter(|&c| c == char::REPLACEMENT_CHARACTER)
+                .count();
+            let ratio = replaced as f64 / total as f64;
+            if ratio > MAX_REPLACEMENT_CHAR_RATIO {
+                return Err(ScrapperError::content_extraction(
+                    url,
+                    format!(
+                        "{replaced} of {total} characters ({:.1}%) couldn't be decoded, so the page's declared charset is probably wrong. Set force_encoding to its real encoding (e.g. \"windows-1252\")",
+                        ratio * 100.0
+                    ),
+                ));
+            }
+        }
+
+        let title = document
+            .select(&self.title_selector)
+            .next()
+            .map(|title| title.text().collect::<String>().trim().to_string())
+            .filter(|title| !title.is_empty());
+
+        Ok(ExtractedContent {
+            text: content,
+            images,
+            title,
+        })
+    }
+
+    /// Apply skipping and filtering to one text node and append what is kept
+    fn collect_text(
+        &self,
+        text: &str,
+        candidate: &Candidate,
+        nodes: &mut NodeCounts,
+        content: &mut String,
+    ) {
+        nodes.total += 1;
+
+        // Skip initial text nodes as specified
+        if nodes.total <= candidate.skip_nodes {
+            nodes.skipped += 1;
+            return;
+        }
+
+        let text = text.trim();
+
+        // Skip empty text nodes
+        if text.is_empty() {
+            return;
+        }
+
+        // Filter out unwanted content
+        if self.should_filter_text(text) {
+            nodes.filtered += 1;
+            return;
+        }
+
+        nodes.kept += 1;
+        content.push_str(text);
+        content.push('\n');
+    }
+
+    /// Parse markup hidden in a comment or `<noscript>` block and collect its text nodes
+    fn collect_fragment_text(
+        &self,
+        markup: &str,
+        candidate: &Candidate,
+        nodes: &mut NodeCounts,
+        content: &mut String,
+    ) {
+        let fragment = Html::parse_fragment(markup);
+        for node in fragment.root_element().descendants() {
+            if let Node::Text(text_node) = node.value() {
+                self.collect_text(text_node, candidate, nodes, content);
+            }
+        }
+    }
+
+    /// Resolve an image source against the page URL and pick its asset file name
+    fn image_asset(page_url: &str, src: &str, number: usize) -> Option<ImageAsset> {
+        let src = src.trim();
+        if src.is_empty() || src.starts_with("data:") {
+            return None;
+        }
+
+        let resolved = Url::parse(page_url).ok()?.join(src).ok()?;
+        let extension = resolved
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .and_then(|name| name.rsplit_once('.'))
+            .map(|(_, ext)| ext.to_ascii_lowercase())
+            .filter(|ext| {
+                !ext.is_empty() && ext.len() <= 5 && ext.chars().all(|c| c.is_ascii_alphanumeric())
+            })
+            .unwrap_or_else(|| "img".to_string());
+
+        Some(ImageAsset {
+            url: resolved.to_string(),
+            file_name: format!("image_{number:03}.{extension}"),
+        })
     }
 
+    /// Whether a text node matches a filter pattern, counting a hit for the first one it matches
     fn should_filter_text(&self, text: &str) -> bool {
+        match self
+            .filter_patterns
+            .iter()
+            .position(|pattern| text.contains(pattern))
+        {
+            Some(index) => {
+                self.filter_hits[index].fetch_add(1, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Each filter pattern with the number of text nodes it removed so far, in config order
+    pub fn filter_hits(&self) -> Vec<(String, usize)> {
         self.filter_patterns
             .iter()
-            .any(|pattern| text.contains(pattern))
+            .zip(&self.filter_hits)
+            .map(|(pattern, hits)| (pattern.clone(), hits.load(Ordering::Relaxed)))
+            .collect()
+    }
+}
+
+/// Drop the first `leading` and last `trailing` lines of extracted text
+fn trim_lines(content: &str, leading: usize, trailing: usize) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let end = lines.len().saturating_sub(trailing);
+    lines
+        .get(leading..end)
+        .unwrap_or_default()
+        .iter()
+        .map(|line| format!("{line}\n"))
+        .collect()
+}
+
+/// Text node counts gathered while extracting, used to explain extraction failures
+#[derive(Debug, Default)]
+struct NodeCounts {
+    total: usize,
+    skipped: usize,
+    filtered: usize,
+    kept: usize,
+}
+
+impl NodeCounts {
+    fn describe(&self) -> String {
+        format!(
+            "Text nodes: {} total, {} skipped, {} filtered, {} kept",
+            self.total, self.skipped, self.filtered, self.kept
+        )
+    }
+
+    /// Suggest lowering `skip_text_nodes` when skipping consumed most of the page
+    fn skip_hint(&self) -> String {
+        if self.skipped > 0 && self.skipped * 2 >= self.total {
+            format!(
+                " skip_text_nodes skipped {} of {} text nodes; try lowering skip_text_nodes.",
+                self.skipped, self.total
+            )
+        } else {
+            String::new()
+        }
     }
 }
 
+/// What a successful `scrape_chapter` call wrote to disk
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ScrapeOutcome {
+    /// Bytes written for the chapter, including downloaded images
+    pub bytes_written: u64,
+    /// The existing file was kept because the new content wasn't larger enough
+    pub kept_existing: bool,
+    /// Nothing was written because no content was left after filtering
+    pub skipped_empty: bool,
+    /// In refresh mode, how the content compares with the file it replaces
+    pub refresh: Option<RefreshChange>,
+}
+
+/// Called with each URL before it is requested, returning extra headers to send with it, e.g.
+/// a per-request signature. For embedding; the command line has no way to set one.
+pub type RequestHook = Arc<dyn Fn(&str) -> Vec<(String, String)> + Send + Sync>;
+
 pub struct WebScraper {
     client: reqwest::Client,
-    extractor: ContentExtractor,
+    request_hook: Option<RequestHook>,
+    extractor: Arc<ContentExtractor>,
+    /// Permits for `max_concurrent_writes`, taken for each file or database write
+    writes: Semaphore,
     config: Config,
+    html_dumps: AtomicUsize,
+    warc: Option<WarcWriter>,
+    /// Headless browser for `render = true`, launched on first use
+    #[cfg(feature = "render")]
+    renderer: tokio::sync::OnceCell<PageRenderer>,
+    /// Chapters go to this database instead of text files when `sqlite_database` is set
+    #[cfg(feature = "sqlite")]
+    database: Option<SqliteSink>,
+}
+
+/// The HTTP client for a run: timeout, user agent, credentials, cookies and TLS settings from
+/// the config. Everything fetched over HTTP (chapters, images, an input CSV URL) goes through it.
+pub fn http_client(config: &Config) -> ScrapperResult<reqwest::Client> {
+    let mut builder = reqwest::Client::builder()
+        .timeout(Duration::from_secs(config.request_timeout_secs))
+        .user_agent(&config.user_agent);
+
+    let mut default_headers = HeaderMap::new();
+    if let Some(accept_language) = &config.accept_language {
+        let value = HeaderValue::from_str(accept_language).map_err(|e| {
+            ScrapperError::validation(
+                "accept_language",
+                format!("'{accept_language}' is not a valid header value: {e}"),
+            )
+        })?;
+        default_headers.insert(ACCEPT_LANGUAGE, value);
+    }
+    if let Some(auth) = &config.auth {
+        default_headers.insert(AUTHORIZATION, authorization_header(auth)?);
+    }
+    builder = builder.default_headers(default_headers);
+
+    if let Some(cookies_file) = &config.cookies_file {
+        builder = builder.cookie_provider(cookies::load_jar(cookies_file)?);
+    }
+
+    if let Some(cert_path) = &config.root_cert {
+        builder = builder.add_root_certificate(load_root_cert(cert_path)?);
+    }
+
+    if config.danger_accept_invalid_certs {
+        eprintln!(
+            "⚠️  WARNING: TLS certificate verification is DISABLED (danger_accept_invalid_certs)."
+        );
+        eprintln!(
+            "⚠️  Connections can be intercepted; only use this for trusted internal or test servers."
+        );
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+
+    builder
+        .build()
+        .map_err(|e| ScrapperError::config(format!("Failed to create HTTP client: {e}")))
+}
+
+/// Load a root certificate from a PEM or DER file
+fn load_root_cert(path: &Path) -> ScrapperResult<reqwest::Certificate> {
+    let bytes = std::fs::read(path).map_err(|e| {
+        ScrapperError::file_system(
+            format!("Failed to read root certificate: {e}"),
+            Some(path.to_path_buf()),
+        )
+    })?;
+
+    reqwest::Certificate::from_pem(&bytes)
+        .or_else(|_| reqwest::Certificate::from_der(&bytes))
+        .map_err(|e| {
+            ScrapperError::validation(
+                "root_cert",
+                format!("{path:?} is not a valid PEM or DER certificate: {e}"),
+            )
+        })
 }
 
 impl WebScraper {
     pub fn new(config: &Config) -> ScrapperResult<Self> {
-        let client = reqwest::Client::builder()
-            .timeout(Duration::from_secs(config.request_timeout_secs))
-            .user_agent(&config.user_agent)
-            .build()
-            .map_err(|e| ScrapperError::config(format!("Failed to create HTTP client: {e}")))?;
+        Self::with_client(config, http_client(config)?)
+    }
 
-        let extractor = ContentExtractor::new(config)?;
+    /// A scraper fetching with `client`, already built from `config` by [`http_client`]
+    pub fn with_client(config: &Config, client: reqwest::Client) -> ScrapperResult<Self> {
+        let extractor = Arc::new(ContentExtractor::new(config)?);
+
+        let warc = config
+            .warc_file
+            .as_deref()
+            .map(WarcWriter::open)
+            .transpose()?;
 
         Ok(Self {
             client,
+            request_hook: None,
             extractor,
+            writes: Semaphore::new(config.max_concurrent_writes),
             config: config.clone(),
+            html_dumps: AtomicUsize::new(0),
+            warc,
+            #[cfg(feature = "render")]
+            renderer: tokio::sync::OnceCell::new(),
+            #[cfg(feature = "sqlite")]
+            database: config
+                .sqlite_database
+                .as_deref()
+                .map(SqliteSink::open)
+                .transpose()?,
+        })
+    }
+
+    /// Add headers from `hook` to every request
+    pub fn with_request_hook(mut self, hook: RequestHook) -> Self {
+        self.request_hook = Some(hook);
+        self
+    }
+
+    /// A GET request for `url` with the request hook's headers, if any
+    fn get(&self, url: &str) -> ScrapperResult<reqwest::RequestBuilder> {
+        let request = self.client.get(url);
+        let Some(hook) = &self.request_hook else {
+            return Ok(request);
+        };
+
+        let mut headers = HeaderMap::new();
+        for (name, value) in hook(url) {
+            let invalid = |e: &dyn std::fmt::Display| {
+                ScrapperError::validation(
+                    "request_hook",
+                    format!("header '{name}' for {url} is invalid: {e}"),
+                )
+            };
+            let header_name = HeaderName::from_bytes(name.as_bytes()).map_err(|e| invalid(&e))?;
+            let header_value = HeaderValue::from_str(&value).map_err(|e| invalid(&e))?;
+            headers.append(header_name, header_value);
+        }
+        Ok(request.headers(headers))
+    }
+
+    /// Wait for one of the `max_concurrent_writes` permits, held until the write is done
+    async fn write_permit(&self) -> ScrapperResult<SemaphorePermit<'_>> {
+        self.writes.acquire().await.map_err(|e| {
+            ScrapperError::task_execution(format!("Failed to wait for a write slot: {e}"))
         })
     }
 
     pub async fn scrape_chapter(
         &self,
         record: &ChapterRecord,
-        output_dir: &Path,
-        stats_pb: Option<&ProgressBar>,
-    ) -> ScrapperResult<()> {
+        chapter_path: &Path,
+        log: Option<&ChapterLog>,
+    ) -> ScrapperResult<ScrapeOutcome> {
         let chapter_name = &record.chapter_number;
         let url = &record.url;
 
-        if let Some(pb) = stats_pb {
-            pb.println(format!("🔄 Starting chapter {chapter_name}: {url}"));
+        if let Some(log) = self.chapter_log(log) {
+            log.info("🔄", &format!("Starting chapter {chapter_name}: {url}"));
         }
+        self.report_step(log, chapter_name, "fetching page");
 
-        // Validate URL format before making request
-        if !url.starts_with("http://") && !url.starts_with("https://") {
+        let (url, html, headers) = match self.fetch(url).await {
+            Ok((html, headers)) => (url.clone(), html, headers),
+            Err(ScrapperError::Http {
+                status: Some(404), ..
+            }) if self.config.fallback_url_template.is_some() => {
+                self.fetch_fallback(record, log).await?
+            }
+            Err(e) => return Err(e),
+        };
+        let url = &url;
+        self.report_step(log, chapter_name, "extracting content");
+
+        if self.config.verbose
+            && let Some(log) = log
+        {
+            log.info(
+                "📄",
+                &format!("Parsing content from {} ({} bytes)", url, html.len()),
+            );
+        }
+
+        // Extract content from HTML, off the async worker threads
+        let html: Arc<str> = html.into();
+        let mut partial = None;
+        let extracted = match self
+            .extractor
+            .extract_content_async_with_partial(Arc::clone(&html), url, &mut partial)
+            .await
+        {
+            Ok(extracted) => extracted,
+            Err(e) => {
+                self.dump_html(record, &html, log).await;
+                if let Some(partial) = partial {
+                    self.save_partial(record, &partial, &e, log).await;
+                }
+                return Err(e);
+            }
+        };
+        let content = extracted.text;
+
+        // Only reachable with `empty_content_policy = "skip"`; otherwise extraction fails
+        if content.trim().is_empty() {
+            if let Some(log) = self.chapter_log(log) {
+                log.info(
+                    "🈳",
+                    &format!("Skipping chapter {chapter_name}: no content left after filtering"),
+                );
+            }
+            return Ok(ScrapeOutcome {
+                skipped_empty: true,
+                ..ScrapeOutcome::default()
+            });
+        }
+
+        #[cfg(feature = "sqlite")]
+        if let Some(database) = &self.database {
+            self.report_step(log, chapter_name, "saving");
+            let _permit = self.write_permit().await?;
+            database
+                .upsert(ChapterRow {
+                    chapter_number: chapter_name.clone(),
+                    url: url.clone(),
+                    title: extracted.title,
+                    content: content.clone(),
+                })
+                .await?;
+            if let Some(log) = self.chapter_log(log) {
+                log.info(
+                    "✅",
+                    &format!(
+                        "Stored chapter {chapter_name} in the database ({} bytes)",
+                        content.len()
+                    ),
+                );
+            }
+            return Ok(ScrapeOutcome {
+                bytes_written: content.len() as u64,
+                kept_existing: false,
+                skipped_empty: false,
+                refresh: None,
+            });
+        }
+
+        if let Some(existing_len) = self.larger_existing_file(chapter_path, content.len()).await {
+            if let Some(log) = self.chapter_log(log) {
+                log.info(
+                    "📎",
+                    &format!(
+                        "Keeping existing chapter {chapter_name} ({existing_len} bytes, new content {} bytes)",
+                        content.len()
+                    ),
+                );
+            }
+            return Ok(ScrapeOutcome {
+                bytes_written: 0,
+                kept_existing: true,
+                skipped_empty: false,
+                refresh: None,
+            });
+        }
+
+        let refresh = self.compare_with_previous(chapter_path, &content).await?;
+        match refresh {
+            Some(RefreshChange::Unchanged) => {
+                if let Some(log) = self.chapter_log(log) {
+                    log.info("🟰", &format!("Chapter {chapter_name} is unchanged"));
+                }
+                return Ok(ScrapeOutcome {
+                    refresh,
+                    ..ScrapeOutcome::default()
+                });
+            }
+            Some(change) => {
+                if let Some(log) = self.chapter_log(log) {
+                    log.info("🔁", &format!("Chapter {chapter_name} changed ({change})"));
+                }
+            }
+            None => {}
+        }
+
+        // Save to file
+        self.report_step(log, chapter_name, "saving");
+        self.save_content(chapter_path, &content).await?;
+        let mut bytes_written = content.len() as u64;
+
+        if self.config.save_headers
+            && let Some(headers) = &headers
+        {
+            let headers_path =
+                FileManager::headers_path_for(self.config.output_layout, chapter_path);
+            self.save_content(&headers_path, headers).await?;
+            bytes_written += headers.len() as u64;
+        }
+
+        if !extracted.images.is_empty() {
+            bytes_written += self
+                .download_images(
+                    chapter_name,
+                    &extracted.images,
+                    &FileManager::assets_dir_for(chapter_path),
+                    log,
+                )
+                .await?;
+        }
+
+        if let Some(log) = self.chapter_log(log) {
+            log.info(
+                "✅",
+                &format!(
+                    "Completed chapter {} ({} bytes)",
+                    chapter_name,
+                    content.len()
+                ),
+            );
+        }
+
+        Ok(ScrapeOutcome {
+            bytes_written,
+            kept_existing: false,
+            skipped_empty: false,
+            refresh,
+        })
+    }
+
+    /// Text nodes removed by each filter pattern during this run
+    pub fn filter_hits(&self) -> Vec<(String, usize)> {
+        self.extractor.filter_hits()
+    }
+
+    /// With the overwrite-smaller policy, the size of an existing chapter file that new content
+    /// of `new_len` bytes isn't large enough to replace
+    async fn larger_existing_file(&self, chapter_path: &Path, new_len: usize) -> Option<u64> {
+        if self.config.existing_policy != ExistingPolicy::OverwriteSmaller {
+            return None;
+        }
+
+        let existing_len = fs::metadata(chapter_path).await.ok()?.len();
+        let required = existing_len as f64 * self.config.overwrite_min_growth;
+        (existing_len > 0 && (new_len as f64) < required).then_some(existing_len)
+    }
+
+    /// In refresh mode, compare new content with the existing chapter file, keeping a backup of
+    /// it when it changed and `refresh_keep_backup` is set
+    async fn compare_with_previous(
+        &self,
+        chapter_path: &Path,
+        content: &str,
+    ) -> ScrapperResult<Option<RefreshChange>> {
+        if self.config.existing_policy != ExistingPolicy::Refresh {
+            return Ok(None);
+        }
+        let Ok(previous) = fs::read(chapter_path).await else {
+            return Ok(None);
+        };
+
+        let change = RefreshChange::between(&previous, content);
+        if change != RefreshChange::Unchanged && self.config.refresh_keep_backup {
+            let backup_path = FileManager::backup_path_for(chapter_path);
+            fs::write(&backup_path, &previous).await.map_err(|e| {
+                ScrapperError::file_system(
+                    format!("Failed to back up previous chapter version: {e}"),
+                    Some(backup_path.clone()),
+                )
+            })?;
+        }
+
+        Ok(Some(change))
+    }
+
+    /// Show what an in-flight chapter is doing on its own progress line, when enabled
+    fn report_step(&self, log: Option<&ChapterLog>, chapter_number: &str, step: &str) {
+        if self.config.chapter_progress
+            && let Some(log) = log
+        {
+            log.set_step(format!("chapter {chapter_number}: {step}"));
+        }
+    }
+
+    /// Where routine per-chapter lines go: nowhere unless `per_chapter_logging` or `verbose`
+    fn chapter_log<'a>(&self, log: Option<&'a ChapterLog>) -> Option<&'a ChapterLog> {
+        log.filter(|_| self.config.per_chapter_logging || self.config.verbose)
+    }
+
+    /// Retry a chapter whose URL returned 404 against `fallback_url_template`,
+    /// returning the fallback URL along with its page
+    async fn fetch_fallback(
+        &self,
+        record: &ChapterRecord,
+        log: Option<&ChapterLog>,
+    ) -> ScrapperResult<(String, String, Option<String>)> {
+        let Some(template) = &self.config.fallback_url_template else {
+            return Err(ScrapperError::config("No fallback_url_template configured"));
+        };
+        let fallback = urls::fallback_url(template, &record.chapter_number);
+
+        if let Some(log) = self.chapter_log(log) {
+            log.info(
+                "↪️",
+                &format!(
+                    "Chapter {}: {} returned 404, trying {fallback}",
+                    record.chapter_number, record.url
+                ),
+            );
+        }
+        self.report_step(log, &record.chapter_number, "fetching fallback page");
+
+        // The fallback may point at another host, so the host filter applies again
+        if let Some(reason) =
+            HostFilter::new(&self.config.allowed_hosts, &self.config.blocked_hosts)
+                .rejection_reason(&fallback)
+        {
             return Err(ScrapperError::validation(
-                "url",
-                format!("Invalid URL format: '{url}'. URLs must start with http:// or https://",),
+                "fallback_url_template",
+                format!("fallback URL {fallback} skipped: {reason}"),
             ));
         }
 
+        let (html, headers) = self.fetch(&fallback).await?;
+        if let Some(log) = self.chapter_log(log) {
+            log.info(
+                "🔀",
+                &format!(
+                    "Chapter {} fetched from fallback URL {fallback}",
+                    record.chapter_number
+                ),
+            );
+        }
+
+        Ok((fallback, html, headers))
+    }
+
+    /// Fetch a page's HTML from any allowed URL scheme, without extracting content
+    pub async fn fetch_page(&self, url: &str) -> ScrapperResult<String> {
+        self.fetch(url).await.map(|(html, _)| html)
+    }
+
+    /// Validate a URL and fetch its HTML, along with the rendered response headers for HTTP pages
+    async fn fetch(&self, url: &str) -> ScrapperResult<(String, Option<String>)> {
+        // Validate URL format before making request
+        let parsed_url = urls::validate_url(url, &self.config.allowed_schemes)
+            .map_err(|message| ScrapperError::validation("url", message))?;
+
+        if parsed_url.scheme() == "file" {
+            Ok((self.read_local_page(&parsed_url).await?, None))
+        } else if self.config.render {
+            Ok((self.render_page(url).await?, None))
+        } else {
+            let (html, headers) = self.fetch_html(url).await?;
+            Ok((html, Some(headers)))
+        }
+    }
+
+    /// Fetch a page over HTTP and return its body along with its rendered status and headers,
+    /// failing on non-success statuses
+    async fn fetch_html(&self, url: &str) -> ScrapperResult<(String, String)> {
         // Fetch the web page with detailed error handling
-        let response = match self.client.get(url).send().await {
+        let response = match self.get(url)?.send().await {
             Ok(response) => response,
             Err(e) => {
                 // Check for specific error types
@@ -201,14 +1054,66 @@ impl WebScraper {
             }
         };
 
-        // Check HTTP status
         let status = response.status();
+        let response_headers = response.headers().clone();
+        let headers = render_headers(url, &response);
+        let status_line = format!("{:?} {}", response.version(), status);
+
+        // Read the raw response body
+        let body = match response.bytes().await {
+            Ok(body) => body,
+            Err(_) if !status.is_success() => Default::default(),
+            Err(e) => {
+                return Err(ScrapperError::web_scraping(
+                    url,
+                    format!("Failed to read response body: {e}"),
+                ));
+            }
+        };
+
+        // Archive every response received, including error pages
+        if let Some(warc) = &self.warc {
+            warc.write_exchange(&HttpExchange {
+                url,
+                user_agent: &self.config.user_agent,
+                accept_language: self.config.accept_language.as_deref(),
+                status_line,
+                response_headers: &response_headers,
+                body: &body,
+            })
+            .await?;
+        }
+
+        let html = decode_body(
+            &body,
+            &response_headers,
+            self.config.force_encoding.as_deref(),
+        );
+
+        // Check HTTP status. Redirects that weren't followed end up here too: their body is a
+        // stub page, never chapter content.
         if !status.is_success() {
             let status_code = status.as_u16();
-            let error_body = response
-                .text()
-                .await
-                .unwrap_or_else(|_| "Unknown error".to_string());
+            if status.is_redirection() {
+                let target = response_headers
+                    .get(LOCATION)
+                    .and_then(|location| location.to_str().ok())
+                    .map_or_else(
+                        || "without a Location header".to_string(),
+                        |location| format!("to {location}"),
+                    );
+                return Err(ScrapperError::http(
+                    url,
+                    Some(status_code),
+                    format!("HTTP {status_code} - redirect {target} wasn't followed"),
+                ));
+            }
+
+            let error_body = if html.is_empty() {
+                "Unknown error"
+            } else {
+                html.as_str()
+            };
 
             return Err(ScrapperError::http(
                 url,
@@ -221,40 +1126,201 @@ impl WebScraper {
             ));
         }
 
-        // Read response body
-        let html = response.text().await.map_err(|e| {
-            ScrapperError::web_scraping(url, format!("Failed to read response body: {e}"))
-        })?;
+        Ok((html, headers))
+    }
 
-        if self.config.verbose {
-            if let Some(pb) = stats_pb {
-                pb.println(format!(
-                    "📄 Parsing content from {} ({} bytes)",
-                    url,
-                    html.len()
-                ));
+    /// Load a page in the headless browser and return the rendered DOM. Rendered pages have no
+    /// status or headers to check, so they are not archived or given a headers sidecar.
+    #[cfg(feature = "render")]
+    async fn render_page(&self, url: &str) -> ScrapperResult<String> {
+        let renderer = self
+            .renderer
+            .get_or_try_init(|| PageRenderer::launch(&self.config))
+            .await?;
+        renderer.render(url).await
+    }
+
+    /// Without the `render` feature, `render = true` is rejected by config validation
+    #[cfg(not(feature = "render"))]
+    async fn render_page(&self, url: &str) -> ScrapperResult<String> {
+        Err(ScrapperError::render(
+            url,
+            "this build has no browser support (built without the `render` feature)",
+        ))
+    }
+
+    /// Read a page from a `file://` URL
+    async fn read_local_page(&self, url: &Url) -> ScrapperResult<String> {
+        let path = url
+            .to_file_path()
+            .map_err(|_| ScrapperError::validation("url", format!("Invalid file URL: '{url}'")))?;
+
+        fs::read_to_string(&path).await.map_err(|e| {
+            ScrapperError::file_system(format!("Failed to read local page: {e}"), Some(path))
+        })
+    }
+
+    /// Save text rejected as too short to `save_partial_on_failure`, headed by the reason, so
+    /// near-misses can be fixed by hand. The chapter still counts as failed.
+    async fn save_partial(
+        &self,
+        record: &ChapterRecord,
+        text: &str,
+        error: &ScrapperError,
+        log: Option<&ChapterLog>,
+    ) {
+        let Some(partial_dir) = &self.config.save_partial_on_failure else {
+            return;
+        };
+
+        let partial_path = partial_dir.join(bounded_file_name(
+            &record.base_name(),
+            Some("txt"),
+            self.config.max_filename_len,
+        ));
+        let note = format!(
+            "# Partial content of chapter {} from {}\n# Rejected: {}\n\n{text}",
+            record.chapter_number,
+            record.url,
+            error.user_friendly_message()
+        );
+        let result = match fs::create_dir_all(partial_dir).await {
+            Ok(()) => fs::write(&partial_path, note).await,
+            Err(e) => Err(e),
+        };
+
+        if let Some(log) = log {
+            match result {
+                Ok(()) => log.info("🩹", &format!("Saved partial content to {partial_path:?}")),
+                Err(e) => log.warning(
+                    "⚠️",
+                    &format!("Failed to save partial content to {partial_path:?}: {e}"),
+                ),
             }
         }
+    }
+
+    /// Save the raw HTML of a page that failed extraction, within the configured caps
+    async fn dump_html(&self, record: &ChapterRecord, html: &str, log: Option<&ChapterLog>) {
+        let Some(dump_dir) = &self.config.dump_html_on_failure else {
+            return;
+        };
+
+        // Reserve a dump slot; give up once the per-run cap is reached
+        if self
+            .html_dumps
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| {
+                (count < self.config.max_html_dumps).then_some(count + 1)
+            })
+            .is_err()
+        {
+            return;
+        }
 
-        // Extract content from HTML
-        let content = self.extractor.extract_content(&html, url)?;
+        let mut end = html.len().min(self.config.max_html_dump_bytes);
+        while !html.is_char_boundary(end) {
+            end -= 1;
+        }
 
-        // Save to file
-        let file_path = output_dir.join(record.file_name());
-        self.save_content(&file_path, &content).await?;
-
-        if let Some(pb) = stats_pb {
-            pb.println(format!(
-                "✅ Completed chapter {} ({} bytes)",
-                chapter_name,
-                content.len()
+        let dump_path = dump_dir.join(bounded_file_name(
+            &record.base_name(),
+            Some("html"),
+            self.config.max_filename_len,
+        ));
+        let result = match fs::create_dir_all(dump_dir).await {
+            Ok(()) => fs::write(&dump_path, &html[..end]).await,
+            Err(e) => Err(e),
+        };
+
+        if let Some(log) = log {
+            match result {
+                Ok(()) => log.info("🧾", &format!("Saved raw HTML to {dump_path:?}")),
+                Err(e) => log.warning(
+                    "⚠️",
+                    &format!("Failed to save raw HTML to {dump_path:?}: {e}"),
+                ),
+            }
+        }
+    }
+
+    /// Download a chapter's images into its assets folder; failed images are reported and skipped.
+    /// Returns the number of bytes written.
+    async fn download_images(
+        &self,
+        chapter_number: &str,
+        images: &[ImageAsset],
+        assets_dir: &Path,
+        log: Option<&ChapterLog>,
+    ) -> ScrapperResult<u64> {
+        fs::create_dir_all(assets_dir).await.map_err(|e| {
+            ScrapperError::file_system(
+                format!("Failed to create assets directory: {e}"),
+                Some(assets_dir.to_path_buf()),
+            )
+        })?;
+
+        let mut bytes_written = 0;
+        for (index, image) in images.iter().enumerate() {
+            self.report_step(
+                log,
+                chapter_number,
+                &format!("image {}/{}", index + 1, images.len()),
+            );
+            let bytes = match self.fetch_bytes(&image.url).await {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    if let Some(log) = log {
+                        log.warning(
+                            "⚠️",
+                            &format!("Skipping image: {}", e.user_friendly_message()),
+                        );
+                    }
+                    continue;
+                }
+            };
+
+            let image_path = assets_dir.join(&image.file_name);
+            fs::write(&image_path, &bytes).await.map_err(|e| {
+                ScrapperError::file_system(
+                    format!("Failed to write image: {e}"),
+                    Some(image_path.clone()),
+                )
+            })?;
+            bytes_written += bytes.len() as u64;
+        }
+
+        Ok(bytes_written)
+    }
+
+    async fn fetch_bytes(&self, url: &str) -> ScrapperResult<Vec<u8>> {
+        let response = self.get(url)?.send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            return Err(ScrapperError::http(
+                url,
+                Some(status.as_u16()),
+                format!("HTTP {}", status.as_u16()),
             ));
         }
 
-        Ok(())
+        let bytes = response.bytes().await?;
+        Ok(bytes.to_vec())
     }
 
     async fn save_content(&self, file_path: &Path, content: &str) -> ScrapperResult<()> {
+        let _permit = self.write_permit().await?;
+
+        if let Some(parent) = file_path.parent()
+            && !parent.exists()
+        {
+            fs::create_dir_all(parent).await.map_err(|e| {
+                ScrapperError::file_system(
+                    format!("Failed to create chapter directory: {e}"),
+                    Some(parent.to_path_buf()),
+                )
+            })?;
+        }
+
         let mut file = File::create(file_path).await.map_err(|e| {
             ScrapperError::file_system(
                 format!("Failed to create file: {e}"),
@@ -280,3 +1346,519 @@ impl WebScraper {
         Ok(())
     }
 }
+
+/// The `Authorization` header value for configured credentials, marked sensitive so it stays
+/// out of debug output
+fn authorization_header(auth: &Auth) -> ScrapperResult<HeaderValue> {
+    let value = match auth {
+        Auth::Basic { user, pass } => {
+            let credentials = format!("{user}:{}", pass.as_deref().unwrap_or_default());
+            format!("Basic {}", BASE64.encode(credentials))
+        }
+        Auth::Bearer { token } => format!("Bearer {}", token.trim()),
+    };
+
+    let mut value = HeaderValue::from_str(&value).map_err(|e| {
+        ScrapperError::validation(
+            "auth",
+            format!("credentials can't be sent in a header: {e}"),
+        )
+    })?;
+    value.set_sensitive(true);
+    Ok(value)
+}
+
+/// Decode a response body using `force_encoding` or else the charset from its Content-Type,
+/// defaulting to UTF-8
+fn decode_body(body: &[u8], headers: &HeaderMap, force_encoding: Option<&str>) -> String {
+    let forced = force_encoding.and_then(|label| Encoding::for_label(label.trim().as_bytes()));
+    let declared = headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|content_type| {
+            content_type.split(';').find_map(|param| {
+                let (name, value) = param.trim().split_once('=')?;
+                name.eq_ignore_ascii_case("charset")
+                    .then(|| Encoding::for_label(value.trim_matches('"').as_bytes()))
+                    .flatten()
+            })
+        });
+    let encoding = forced.or(declared).unwrap_or(UTF_8);
+
+    let (text, _, _) = encoding.decode(body);
+    text.into_owned()
+}
+
+/// Headers never written to sidecars since they may carry session credentials
+const UNSAVED_HEADERS: &[&str] = &["set-cookie"];
+
+/// Render a response's URL, status line and headers as plain text for the headers sidecar
+fn render_headers(url: &str, response: &reqwest::Response) -> String {
+    let mut rendered = format!(
+        "URL: {url}\n{:?} {}\n",
+        response.version(),
+        response.status()
+    );
+
+    for (name, value) in response.headers() {
+        if UNSAVED_HEADERS.contains(&name.as_str()) {
+            continue;
+        }
+        rendered.push_str(&format!(
+            "{name}: {}\n",
+            String::from_utf8_lossy(value.as_bytes())
+        ));
+    }
+
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::progress::{ProgressManager, capture_json_output};
+    use crate::test_dir::TempDir;
+    use crate::test_server::serve_once;
+    use crate::types::SelectorSpec;
+
+    fn extractor(skip_text_nodes: usize) -> ContentExtractor {
+        extractor_for(&Config {
+            selector: "main".to_string(),
+            skip_text_nodes,
+            ..Config::default()
+        })
+    }
+
+    fn extractor_for(config: &Config) -> ContentExtractor {
+        ContentExtractor::new(config).unwrap()
+    }
+
+    #[test]
+    fn test_authorization_header() {
+        let basic = Auth::Basic {
+            user: "Aladdin".to_string(),
+            pass: Some("open sesame".to_string()),
+        };
+        assert_eq!(
+            authorization_header(&basic).unwrap(),
+            "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ=="
+        );
+
+        let bearer = Auth::Bearer {
+            token: "abc.def ".to_string(),
+        };
+        let value = authorization_header(&bearer).unwrap();
+        assert_eq!(value, "Bearer abc.def");
+        assert!(value.is_sensitive());
+
+        let broken = Auth::Bearer {
+            token: "line\nbreak".to_string(),
+        };
+        assert!(authorization_header(&broken).is_err());
+    }
+
+    #[test]
+    fn test_request_hook_headers_are_attached() {
+        let scraper = WebScraper::new(&Config::default())
+            .unwrap()
+            .with_request_hook(Arc::new(|url: &str| {
+                vec![("X-Signature".to_string(), format!("sig-{}", url.len()))]
+            }));
+        let request = scraper
+            .get("https://example.com/1")
+            .unwrap()
+            .build()
+            .unwrap();
+        assert_eq!(request.headers()["x-signature"], "sig-21");
+
+        let broken = WebScraper::new(&Config::default())
+            .unwrap()
+            .with_request_hook(Arc::new(|_: &str| {
+                vec![("Bad Name".to_string(), "x".to_string())]
+            }));
+        assert!(broken.get("https://example.com/1").is_err());
+    }
+
+    #[test]
+    fn test_routine_chapter_lines_need_per_chapter_logging() {
+        let (output, _) = capture_json_output();
+        let progress = ProgressManager::with_json_output(1, Some(output)).unwrap();
+        let log = progress.chapter_log(progress.get_stats_pb());
+
+        let quiet = WebScraper::new(&Config::default()).unwrap();
+        assert!(quiet.chapter_log(Some(&log)).is_none());
+
+        let chatty = WebScraper::new(&Config {
+            per_chapter_logging: true,
+            ..Config::default()
+        })
+        .unwrap();
+        assert!(chatty.chapter_log(Some(&log)).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_chapter_messages_are_logged_with_hidden_bars() {
+        let url = serve_once(
+            "200 OK",
+            &[("Content-Type", "text/html")],
+            "<html><body><p>Please sign in</p></body></html>",
+        ) + "/chapter-1";
+        let dump_dir = TempDir::new("hidden_log");
+        let scraper = WebScraper::new(&Config {
+            dump_html_on_failure: Some(dump_dir.path().to_path_buf()),
+            ..Config::default()
+        })
+        .unwrap();
+        let (output, lines) = capture_json_output();
+        let progress = ProgressManager::with_json_output(1, Some(output)).unwrap();
+        let log = progress.chapter_log(progress.get_stats_pb());
+
+        let result = scraper
+            .scrape_chapter(
+                &ChapterRecord::new(url, "1".to_string()),
+                &dump_dir.join("chapter_1.txt"),
+                Some(&log),
+            )
+            .await;
+
+        assert!(result.is_err());
+        let lines = lines.lock().unwrap();
+        assert!(
+            lines
+                .iter()
+                .any(|line| line.contains(r#""event":"log""#) && line.contains("Saved raw HTML")),
+            "{lines:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_redirect_response_is_not_saved() {
+        let url = serve_once(
+            "301 Moved Permanently",
+            &[("Content-Type", "text/html")],
+            "<html><body><div class=\"content-inner\">Moved</div></body></html>",
+        ) + "/chapter-1";
+        let dir = TempDir::new("redirect");
+        let path = dir.join("chapter_1.txt");
+        let scraper = WebScraper::new(&Config::default()).unwrap();
+
+        let result = scraper
+            .scrape_chapter(&ChapterRecord::new(url, "1".to_string()), &path, None)
+            .await;
+
+        assert!(matches!(
+            result,
+            Err(ScrapperError::Http {
+                status: Some(301),
+                ..
+            })
+        ));
+        assert!(!path.exists());
+    }
+
+    #[tokio::test]
+    async fn test_writes_wait_for_a_free_slot() {
+        let scraper = WebScraper::new(&Config {
+            max_concurrent_writes: 1,
+            ..Config::default()
+        })
+        .unwrap();
+        let dir = TempDir::new("write_slot");
+        let path = dir.join("chapter_1.txt");
+
+        let held = scraper.write_permit().await.unwrap();
+        let blocked = tokio::time::timeout(
+            Duration::from_millis(50),
+            scraper.save_content(&path, "text"),
+        )
+        .await;
+        assert!(blocked.is_err());
+        assert!(!path.exists());
+
+        drop(held);
+        scraper.save_content(&path, "text").await.unwrap();
+        assert_eq!(tokio::fs::read_to_string(&path).await.unwrap(), "text");
+    }
+
+    #[test]
+    fn test_filter_hits_are_counted_per_pattern() {
+        let extractor = extractor_for(&Config {
+            selector: "main".to_string(),
+            skip_text_nodes: 0,
+            filter_patterns: vec![
+                "Advertisement".to_string(),
+                "Subscribe".to_string(),
+                "Never seen".to_string(),
+            ],
+            ..Config::default()
+        });
+        let html = format!(
+            "<main><p>Advertisement</p><p>{}</p><p>Advertisement: Subscribe</p><p>Subscribe now</p></main>",
+            "Chapter text. ".repeat(10)
+        );
+
+        extractor
+            .extract_content(&html, "https://example.com/1")
+            .unwrap();
+
+        assert_eq!(
+            extractor.filter_hits(),
+            [
+                ("Advertisement".to_string(), 2),
+                ("Subscribe".to_string(), 1),
+                ("Never seen".to_string(), 0)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_empty_content_policy() {
+        // Only filtered-out text in the content element
+        let html = "<main><p>Advertisement</p><p>Subscribe</p></main>";
+        let config = Config {
+            selector: "main".to_string(),
+            skip_text_nodes: 0,
+            ..Config::default()
+        };
+        assert!(
+            extractor_for(&config)
+                .extract_content(html, "https://example.com/1")
+                .is_err()
+        );
+
+        let skip = Config {
+            empty_content_policy: EmptyContentPolicy::Skip,
+            ..config
+        };
+        let extracted = extractor_for(&skip)
+            .extract_content(html, "https://example.com/1")
+            .unwrap();
+        assert!(extracted.text.is_empty());
+
+        // Too-short content is still an error
+        assert!(
+            extractor_for(&skip)
+                .extract_content("<main><p>Short</p></main>", "https://example.com/1")
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_extracts_content_hidden_in_comments() {
+        let html = include_str!("../tests/fixtures/comment_content.html");
+        let config = Config {
+            selector: "#chapter".to_string(),
+            skip_text_nodes: 0,
+            extract_from_comments: true,
+            ..Config::default()
+        };
+        let extracted = extractor_for(&config)
+            .extract_content(html, "https://example.com/1")
+            .unwrap();
+
+        assert!(
+            extracted
+                .text
+                .contains("The real chapter text starts here.")
+        );
+        assert!(!extracted.text.contains("<p>"));
+
+        // Without the option the comment is ignored and only the decoy remains
+        let err = extractor_for(&Config {
+            extract_from_comments: false,
+            ..config
+        })
+        .extract_content(html, "https://example.com/1")
+        .unwrap_err();
+        assert!(err.to_string().contains("too short"), "{err}");
+    }
+
+    #[test]
+    fn test_extracts_noscript_markup_as_text() {
+        let html = "<main><noscript><p>Hidden chapter paragraph, shown only to browsers without JavaScript.</p><p>It is long enough to pass the quality check.</p></noscript></main>";
+        let config = Config {
+            selector: "main".to_string(),
+            skip_text_nodes: 0,
+            include_noscript: true,
+            ..Config::default()
+        };
+
+        let extracted = extractor_for(&config)
+            .extract_content(html, "https://example.com/1")
+            .unwrap();
+
+        assert!(extracted.text.starts_with("Hidden chapter paragraph"));
+    }
+
+    #[test]
+    fn test_selectors_are_tried_until_one_yields_content() {
+        let chapter = "Chapter text. ".repeat(10);
+        let html = format!(
+            "<article><p>Too short</p></article>\
+             <div id=\"story\"><p>Chapter 3</p><div class=\"ad\">Buy now</div><p>{chapter}</p></div>"
+        );
+        let spec = |selector: &str, skip_text_nodes, exclude: &[&str]| SelectorSpec {
+            selector: selector.to_string(),
+            skip_text_nodes,
+            exclude: exclude.iter().map(|s| s.to_string()).collect(),
+        };
+        let config = Config {
+            skip_text_nodes: 0,
+            selectors: vec![
+                spec("main", None, &[]),
+                spec("article", None, &[]),
+                spec("#story", Some(1), &[".ad"]),
+            ],
+            ..Config::default()
+        };
+
+        let extracted = extractor_for(&config)
+            .extract_content(&html, "https://example.com/1")
+            .unwrap();
+        assert_eq!(extracted.text, format!("{}\n", chapter.trim()));
+
+        let error = extractor_for(&config)
+            .extract_content(
+                "<article><p>Too short</p></article>",
+                "https://example.com/1",
+            )
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("'main': No element found"), "{error}");
+        assert!(
+            error.contains("'article': Extracted content is too short"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn test_detects_text_decoded_with_wrong_charset() {
+        // "café" repeated, encoded as windows-1252 but declared as UTF-8
+        let text = "Un café crème. ".repeat(20);
+        let (body, _, _) = encoding_rs::WINDOWS_1252.encode(&text);
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("text/html; charset=utf-8"),
+        );
+        let wrapped = |text: String| format!("<main><p>{text}</p></main>");
+
+        let config = Config {
+            selector: "main".to_string(),
+            skip_text_nodes: 0,
+            detect_encoding_errors: true,
+            ..Config::default()
+        };
+        let extractor = extractor_for(&config);
+
+        let mojibake = wrapped(decode_body(&body, &headers, None));
+        let error = extractor
+            .extract_content(&mojibake, "https://example.com/1")
+            .unwrap_err();
+        assert!(error.to_string().contains("force_encoding"));
+
+        let forced = wrapped(decode_body(&body, &headers, Some("windows-1252")));
+        let extracted = extractor
+            .extract_content(&forced, "https://example.com/1")
+            .unwrap();
+        assert!(extracted.text.starts_with("Un café crème."));
+    }
+
+    #[test]
+    fn test_short_content_suggests_lowering_skip() {
+        let html = "<main><p>Title</p><p>Byline</p><p>Short but real chapter text.</p></main>";
+
+        let err = extractor(3)
+            .extract_content(html, "https://example.com/1")
+            .unwrap_err()
+            .to_string();
+
+        assert!(err.contains("3 total, 3 skipped"), "{err}");
+        assert!(err.contains("try lowering skip_text_nodes"), "{err}");
+    }
+
+    #[test]
+    fn test_short_content_without_skip_has_no_skip_hint() {
+        let html = "<main><p>Title</p><p>Short but real chapter text.</p></main>";
+
+        let err = extractor(0)
+            .extract_content(html, "https://example.com/1")
+            .unwrap_err()
+            .to_string();
+
+        assert!(err.contains("too short"), "{err}");
+        assert!(err.contains("0 skipped"), "{err}");
+        assert!(!err.contains("skip_text_nodes"), "{err}");
+    }
+
+    #[test]
+    fn test_short_content_is_handed_back_as_partial() {
+        let mut partial = None;
+        let result = extractor(0).extract_content_with_partial(
+            "<main><p>Almost a chapter.</p></main>",
+            "https://example.com/1",
+            &mut partial,
+        );
+
+        assert!(result.is_err());
+        assert_eq!(partial.as_deref(), Some("Almost a chapter.\n"));
+
+        let mut partial = None;
+        let result = extractor(0).extract_content_with_partial(
+            "<div>No main element</div>",
+            "https://example.com/1",
+            &mut partial,
+        );
+        assert!(result.is_err());
+        assert_eq!(partial, None);
+    }
+
+    #[tokio::test]
+    async fn test_async_extraction_matches_sync() {
+        let extractor = Arc::new(extractor(0));
+        let html = format!("<main><p>{}</p></main>", "Chapter text. ".repeat(10));
+
+        let extracted = extractor
+            .extract_content_async(html.as_str().into(), "https://example.com/1")
+            .await
+            .unwrap();
+        let expected = extractor
+            .extract_content(&html, "https://example.com/1")
+            .unwrap();
+        assert_eq!(extracted.text, expected.text);
+
+        let mut partial = None;
+        let result = extractor
+            .extract_content_async_with_partial(
+                "<main><p>Almost a chapter.</p></main>".into(),
+                "https://example.com/1",
+                &mut partial,
+            )
+            .await;
+        assert!(result.is_err());
+        assert_eq!(partial.as_deref(), Some("Almost a chapter.\n"));
+    }
+
+    #[test]
+    fn test_trims_leading_and_trailing_lines() {
+        let html = format!(
+            "<main><p>Home &gt; Novel</p><p>{}</p><p>Chapter text ends.</p><p>Next chapter</p><p>Comments</p></main>",
+            "Chapter text. ".repeat(10)
+        );
+        let config = Config {
+            selector: "main".to_string(),
+            skip_text_nodes: 0,
+            trim_leading_lines: 1,
+            trim_trailing_lines: 2,
+            ..Config::default()
+        };
+
+        let extracted = extractor_for(&config)
+            .extract_content(&html, "https://example.com/1")
+            .unwrap();
+
+        assert!(extracted.text.starts_with("Chapter text."));
+        assert!(extracted.text.ends_with("Chapter text ends.\n"));
+        assert_eq!(trim_lines("a\nb\n", 2, 1), "");
+    }
+}